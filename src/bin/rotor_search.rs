@@ -0,0 +1,168 @@
+// Search the indexes saved by rotor from a terminal, without starting the GUI
+//
+// usage: rotor-search [options] <query>
+// exit codes: 0 results found, 1 no result, 2 bad arguments, 3 no readable index
+
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::{env, fs};
+
+#[allow(dead_code)]
+#[path = "../module/searcher/volume/file_map.rs"]
+mod file_map;
+
+use file_map::{FileMap, SearchResultItem};
+
+const EXIT_FOUND: u8 = 0;
+const EXIT_NOT_FOUND: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_NO_INDEX: u8 = 3;
+
+const USAGE: &str = "\
+usage: rotor-search [options] <query>
+
+options:
+  -n, --limit <N>        print at most N results, 0 for all (default 20)
+  -s, --offset <N>       skip the first N results (default 0)
+  -f, --format <FORMAT>  text, json (one object per line) or null (NUL separated paths)
+  -d, --index-dir <DIR>  folder of the .fd files (default: userdata next to this program)
+  -h, --help             show this message
+
+exit codes: 0 results found, 1 no result, 2 bad arguments, 3 no readable index";
+
+#[derive(PartialEq)]
+enum Format {
+    Text,
+    Json,
+    Null,
+}
+
+struct Options {
+    query: String,
+    limit: usize,
+    offset: usize,
+    format: Format,
+    index_dir: PathBuf,
+}
+
+fn default_index_dir() -> PathBuf {
+    let app_path = env::current_exe()
+        .ok()
+        .and_then(|exe_path| exe_path.parent().map(Path::to_path_buf))
+        .unwrap_or_else(|| PathBuf::from("."));
+    app_path.join("userdata")
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, Box<dyn Error>> {
+    let mut options = Options {
+        query: String::new(),
+        limit: 20,
+        offset: 0,
+        format: Format::Text,
+        index_dir: default_index_dir(),
+    };
+    let mut query_parts = Vec::new();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("missing value for {}", name));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-n" | "--limit" => options.limit = value(&arg)?.parse()?,
+            "-s" | "--offset" => options.offset = value(&arg)?.parse()?,
+            "-d" | "--index-dir" => options.index_dir = PathBuf::from(value(&arg)?),
+            "-f" | "--format" => {
+                options.format = match value(&arg)?.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    "null" => Format::Null,
+                    other => return Err(format!("unknown format: {}", other).into()),
+                }
+            },
+            "--" => { query_parts.extend(args.by_ref()); },
+            _ if arg.starts_with('-') && arg.len() > 1 => return Err(format!("unknown option: {}", arg).into()),
+            _ => query_parts.push(arg),
+        }
+    }
+
+    options.query = query_parts.join(" ");
+    if options.query.is_empty() { return Err("missing query".into()); }
+    Ok(Some(options))
+}
+
+fn write_item(out: &mut impl Write, item: &SearchResultItem, format: &Format) -> io::Result<()> {
+    let full_path = item.path.clone() + item.file_name.as_str();
+    match format {
+        Format::Text => writeln!(out, "{}", full_path),
+        Format::Null => write!(out, "{}\0", full_path),
+        Format::Json => writeln!(out, "{}", serde_json::json!({
+            "name": item.file_name,
+            "path": full_path,
+            "rank": item.rank,
+        })),
+    }
+}
+
+fn run(options: Options) -> Result<u8, Box<dyn Error>> {
+    let mut index_paths = fs::read_dir(&options.index_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "fd"))
+        .collect::<Vec<_>>();
+    index_paths.sort();
+
+    let (_stop_sender, stop_receiver) = mpsc::channel::<()>(); // never stopped
+    let max = if options.limit == 0 { 0 } else { options.offset + options.limit };
+    let mut items = Vec::new();
+    let mut index_read = 0;
+    for index_path in index_paths {
+        let mut file_map = FileMap::new();
+        match file_map.read(&index_path.to_string_lossy()) {
+            Ok(_) => {
+                index_read += 1;
+                items.append(&mut file_map.search_top(&options.query, max, &stop_receiver).unwrap_or_default());
+            },
+            Err(e) => eprintln!("rotor-search: skip {}: {}", index_path.display(), e),
+        }
+    }
+    if index_read == 0 {
+        eprintln!("rotor-search: no index in {}, open the search window of rotor once to build it", options.index_dir.display());
+        return Ok(EXIT_NO_INDEX);
+    }
+
+    items.sort_by_key(|item| std::cmp::Reverse(item.rank)); // sort by rank like the search window
+    let items = items.iter().skip(options.offset);
+    let items = if options.limit == 0 { items.collect::<Vec<_>>() } else { items.take(options.limit).collect() };
+
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    for item in &items {
+        write_item(&mut out, item, &options.format)?;
+    }
+    out.flush()?;
+
+    Ok(if items.is_empty() { EXIT_NOT_FOUND } else { EXIT_FOUND })
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::from(EXIT_FOUND);
+        },
+        Err(e) => {
+            eprintln!("rotor-search: {}\n\n{}", e, USAGE);
+            return ExitCode::from(EXIT_USAGE);
+        },
+    };
+
+    match run(options) {
+        Ok(code) => ExitCode::from(code),
+        Err(e) => {
+            eprintln!("rotor-search: {}", e);
+            ExitCode::from(EXIT_NO_INDEX)
+        },
+    }
+}
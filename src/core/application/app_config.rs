@@ -9,7 +9,18 @@ use slint::select_bundled_translation;
 
 use crate::util::{file_util, log_util};
 use crate::util::sys_util;
-use crate::ui::{SearchWindow, SettingWindow, ToolbarWindow};
+use crate::ui::{SearchWindow, SettingWindow, ToolbarWindow, DuplicateWindow, DiagnosticsWindow, SnapshotWindow, DiskUsageWindow, RecentWindow};
+
+// A keyword of the searcher, "g cats" opens a url made from the template, ">build" runs a command
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Alias {
+    pub keyword: String, // starting with '>' for a command
+    pub target: String, // a url or folder to open, or a command line, {query} is replaced by the rest of the query
+    #[serde(default = "default_string")]
+    pub dir: String, // where a command runs, the home folder when empty
+    #[serde(default = "default_false")]
+    pub is_folder: bool, // decided when the alias is saved, the target is not checked while typing
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
@@ -25,12 +36,66 @@ pub struct Config {
     shortcuts: HashMap<String, String>,
     #[serde(default = "default_zoom_delta")]
     zoom_delta: u8,
+    #[serde(default = "default_vec_string")]
+    search_roots: Vec<String>,
+    #[serde(default = "default_search_root_refresh")]
+    search_root_refresh: u32,
+    #[serde(default = "default_u8")]
+    index_residency: u8, // 0: release on blur, 1: release after idle minutes, 2: always resident
+    #[serde(default = "default_index_idle_minutes")]
+    index_idle_minutes: u32,
+    #[serde(default = "default_u32")]
+    index_memory_budget: u32, // MB, 0 means no limit
+    #[serde(default = "default_false")]
+    query_server: bool,
+    #[serde(default = "default_query_server_port")]
+    query_server_port: u16,
+    #[serde(default = "default_false")]
+    http_server: bool,
+    #[serde(default = "default_http_server_port")]
+    http_server_port: u16,
+    #[serde(default = "default_false")]
+    search_archives: bool, // match the entries of zip archives found in the index
+    #[serde(default = "default_false")]
+    content_search: bool,
+    #[serde(default = "default_content_extensions")]
+    content_extensions: Vec<String>, // lowercased, without the dot
+    #[serde(default = "default_content_max_kb")]
+    content_max_kb: u32,
+    #[serde(default = "default_aliases")]
+    aliases: Vec<Alias>,
+    #[serde(default = "default_true")]
+    search_history: bool, // the queries results were opened from, shown while the search box is empty
+    #[serde(default = "default_false")]
+    clip_history: bool,
+    #[serde(default = "default_clip_history_size")]
+    clip_history_size: u32, // entries kept
+    #[serde(default = "default_clip_exclusions")]
+    clip_exclusions: Vec<String>, // lowercased executable names whose copies are not recorded
 }
 
 fn default_false() -> bool { false }
+fn default_true() -> bool { true }
 fn default_u8() -> u8 { 0 }
 fn default_zoom_delta() -> u8 { 2 }
 fn default_string() -> String { String::new() }
+fn default_vec_string() -> Vec<String> { Vec::new() }
+fn default_u32() -> u32 { 0 }
+fn default_search_root_refresh() -> u32 { 30 }
+fn default_index_idle_minutes() -> u32 { 10 }
+fn default_query_server_port() -> u16 { 47126 }
+fn default_http_server_port() -> u16 { 47127 } // next to the query server, clear of the ports of dev servers
+fn default_content_max_kb() -> u32 { 256 }
+fn default_content_extensions() -> Vec<String> {
+    ["txt", "md", "log", "ini", "json", "toml", "yaml", "yml", "xml", "csv", "html", "css",
+     "rs", "py", "js", "ts", "c", "h", "cpp", "java", "go", "cs", "sql", "sh", "bat", "ps1"]
+        .into_iter().map(String::from).collect()
+}
+fn default_aliases() -> Vec<Alias> { Vec::new() }
+fn default_clip_history_size() -> u32 { 200 }
+fn default_clip_exclusions() -> Vec<String> {
+    ["keepass.exe", "keepassxc.exe", "1password.exe", "bitwarden.exe"].into_iter().map(String::from).collect()
+}
 fn default_shortcuts() -> HashMap<String, String> { 
     let mut shortcuts = HashMap::new();
     shortcuts.insert("search".into(), "Shift+F".into());
@@ -47,6 +112,11 @@ pub struct AppConfig {
     pub search_win: Option<Weak<SearchWindow>>,
     pub setting_win: Option<Weak<SettingWindow>>,
     pub toolbar_win: Option<Weak<ToolbarWindow>>,
+    pub duplicate_win: Option<Weak<DuplicateWindow>>,
+    pub diagnostics_win: Option<Weak<DiagnosticsWindow>>,
+    pub snapshot_win: Option<Weak<SnapshotWindow>>,
+    pub disk_usage_win: Option<Weak<DiskUsageWindow>>,
+    pub recent_win: Option<Weak<RecentWindow>>,
 }
 
 impl AppConfig {
@@ -67,6 +137,11 @@ impl AppConfig {
             search_win: None,
             setting_win: None,
             toolbar_win: None,
+            duplicate_win: None,
+            diagnostics_win: None,
+            snapshot_win: None,
+            disk_usage_win: None,
+            recent_win: None,
         }
     }
 
@@ -135,6 +210,36 @@ impl AppConfig {
             }
         }
 
+        if let Some(duplicate_win) = &self.duplicate_win {
+            if let Some(duplicate_win) = duplicate_win.upgrade() {
+                duplicate_win.invoke_change_theme(theme as i32);
+            }
+        }
+
+        if let Some(diagnostics_win) = &self.diagnostics_win {
+            if let Some(diagnostics_win) = diagnostics_win.upgrade() {
+                diagnostics_win.invoke_change_theme(theme as i32);
+            }
+        }
+
+        if let Some(snapshot_win) = &self.snapshot_win {
+            if let Some(snapshot_win) = snapshot_win.upgrade() {
+                snapshot_win.invoke_change_theme(theme as i32);
+            }
+        }
+
+        if let Some(disk_usage_win) = &self.disk_usage_win {
+            if let Some(disk_usage_win) = disk_usage_win.upgrade() {
+                disk_usage_win.invoke_change_theme(theme as i32);
+            }
+        }
+
+        if let Some(recent_win) = &self.recent_win {
+            if let Some(recent_win) = recent_win.upgrade() {
+                recent_win.invoke_change_theme(theme as i32);
+            }
+        }
+
         self.config.theme = theme;
         self.save()
             .unwrap_or_else(|err| log_util::log_error(format!("AppConfig save error: {:?}", err)));
@@ -182,6 +287,196 @@ impl AppConfig {
     pub fn get_zoom_delta(&self) -> u8 {
         self.config.zoom_delta
     }
+
+    pub fn add_search_root(&mut self, root: String) -> Result<(), Box<dyn Error>> {
+        if self.config.search_roots.contains(&root) { return Err("Search root already exists".into()); }
+        self.config.search_roots.push(root);
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn remove_search_root(&mut self, root: &str) -> Result<(), Box<dyn Error>> {
+        self.config.search_roots.retain(|r| r != root);
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_search_roots(&self) -> Vec<String> {
+        self.config.search_roots.clone()
+    }
+
+    // minutes between two walks of a root that can not be watched
+    pub fn get_search_root_refresh(&self) -> u32 {
+        self.config.search_root_refresh
+    }
+
+    pub fn set_index_residency(&mut self, residency: u8) -> Result<(), Box<dyn Error>> {
+        self.config.index_residency = residency;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_index_residency(&self) -> u8 {
+        self.config.index_residency
+    }
+
+    pub fn set_index_idle_minutes(&mut self, minutes: u32) -> Result<(), Box<dyn Error>> {
+        self.config.index_idle_minutes = minutes;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_index_idle_minutes(&self) -> u32 {
+        self.config.index_idle_minutes
+    }
+
+    pub fn set_index_memory_budget(&mut self, budget: u32) -> Result<(), Box<dyn Error>> {
+        self.config.index_memory_budget = budget;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_index_memory_budget(&self) -> u32 {
+        self.config.index_memory_budget
+    }
+
+    pub fn set_query_server(&mut self, enable: bool) -> Result<(), Box<dyn Error>> {
+        self.config.query_server = enable;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_query_server(&self) -> bool {
+        self.config.query_server
+    }
+
+    pub fn set_query_server_port(&mut self, port: u16) -> Result<(), Box<dyn Error>> {
+        self.config.query_server_port = port;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_query_server_port(&self) -> u16 {
+        self.config.query_server_port
+    }
+
+    pub fn set_http_server(&mut self, enable: bool) -> Result<(), Box<dyn Error>> {
+        self.config.http_server = enable;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_http_server(&self) -> bool {
+        self.config.http_server
+    }
+
+    pub fn set_http_server_port(&mut self, port: u16) -> Result<(), Box<dyn Error>> {
+        self.config.http_server_port = port;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_http_server_port(&self) -> u16 {
+        self.config.http_server_port
+    }
+
+    pub fn set_search_archives(&mut self, enable: bool) -> Result<(), Box<dyn Error>> {
+        self.config.search_archives = enable;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_search_archives(&self) -> bool {
+        self.config.search_archives
+    }
+
+    pub fn set_content_search(&mut self, enable: bool) -> Result<(), Box<dyn Error>> {
+        self.config.content_search = enable;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_content_search(&self) -> bool {
+        self.config.content_search
+    }
+
+    pub fn set_content_extensions(&mut self, extensions: Vec<String>) -> Result<(), Box<dyn Error>> {
+        self.config.content_extensions = extensions;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_content_extensions(&self) -> Vec<String> {
+        self.config.content_extensions.clone()
+    }
+
+    pub fn set_content_max_kb(&mut self, max_kb: u32) -> Result<(), Box<dyn Error>> {
+        self.config.content_max_kb = max_kb;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_content_max_kb(&self) -> u32 {
+        self.config.content_max_kb
+    }
+
+    // an alias with the same keyword is replaced
+    pub fn add_alias(&mut self, alias: Alias) -> Result<(), Box<dyn Error>> {
+        self.config.aliases.retain(|a| !a.keyword.eq_ignore_ascii_case(&alias.keyword));
+        self.config.aliases.push(alias);
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn remove_alias(&mut self, keyword: &str) -> Result<(), Box<dyn Error>> {
+        self.config.aliases.retain(|a| a.keyword != keyword);
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_aliases(&self) -> Vec<Alias> {
+        self.config.aliases.clone()
+    }
+
+    pub fn set_clip_history(&mut self, enable: bool) -> Result<(), Box<dyn Error>> {
+        self.config.clip_history = enable;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_clip_history(&self) -> bool {
+        self.config.clip_history
+    }
+
+    pub fn set_clip_history_size(&mut self, size: u32) -> Result<(), Box<dyn Error>> {
+        self.config.clip_history_size = size;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_clip_history_size(&self) -> u32 {
+        self.config.clip_history_size
+    }
+
+    pub fn set_clip_exclusions(&mut self, exclusions: Vec<String>) -> Result<(), Box<dyn Error>> {
+        self.config.clip_exclusions = exclusions;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_clip_exclusions(&self) -> Vec<String> {
+        self.config.clip_exclusions.clone()
+    }
+
+    pub fn set_search_history(&mut self, enable: bool) -> Result<(), Box<dyn Error>> {
+        self.config.search_history = enable;
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_search_history(&self) -> bool {
+        self.config.search_history
+    }
 }
 
 static INSTANCE: LazyLock<Mutex<AppConfig>> = LazyLock::new(|| {
@@ -0,0 +1,212 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::UNIX_EPOCH;
+use serde::{Serialize, Deserialize};
+
+use crate::util::{file_util, log_util};
+use super::file_data::SharedVolume;
+use super::volume::{self, SearchResultItem};
+
+// zip based formats, their entries are listed with the zip crate
+const ARCHIVE_EXTENSIONS: [&str; 8] = ["zip", "jar", "war", "apk", "epub", "nupkg", "vsix", "whl"];
+const MAX_ENTRIES: usize = 20000; // per archive, the rest of a huge archive is left out
+const RANK_PENALTY: i8 = 10; // entries of archives come after files with the same name
+
+static REFRESHING: AtomicBool = AtomicBool::new(false);
+
+// The listing of an archive, read again only when its size or modification time changed
+#[derive(Serialize, Deserialize)]
+struct CachedArchive {
+    size: u64,
+    modified: u64, // unix seconds
+    entries: Vec<String>, // file names inside the archive, with '/' as in the archive
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct ArchiveIndex {
+    archives: HashMap<String, CachedArchive>, // by full path of the archive
+}
+
+impl ArchiveIndex {
+    fn new() -> ArchiveIndex {
+        fs::read_to_string(Self::cache_path())
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn global() -> &'static Mutex<ArchiveIndex> {
+        &INSTANCE
+    }
+
+    fn cache_path() -> PathBuf {
+        file_util::get_userdata_path().join("archives.json")
+    }
+
+    fn save(&self) -> Result<(), Box<dyn Error>> {
+        let root_path = file_util::get_userdata_path();
+        if !root_path.exists() { fs::create_dir_all(&root_path)?; }
+        fs::write(Self::cache_path(), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    // A page of the entries of all listed archives whose name matches the query, the best ranked first
+    pub fn search(&self, query: &str, offset: usize, max: usize) -> Vec<SearchResultItem> {
+        let query_lower = query.to_lowercase();
+        let mut matches = Vec::new();
+        for (archive_path, archive) in &self.archives {
+            for entry in &archive.entries {
+                let (_, file_name) = entry.rsplit_once('/').unwrap_or(("", entry));
+                let Some(rank) = volume::match_name(file_name, &query_lower) else { continue; };
+                matches.push((rank, archive_path, entry));
+            }
+        }
+        // the order must not change between pages
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)).then_with(|| a.2.cmp(b.2)));
+
+        matches.into_iter().skip(offset).take(max).map(|(rank, archive_path, entry)| {
+            let (folder, file_name) = entry.rsplit_once('/').unwrap_or(("", entry));
+            let mut path = archive_path.clone() + "\\";
+            if !folder.is_empty() { path += &(folder.replace('/', "\\") + "\\"); }
+            SearchResultItem { path, file_name: file_name.to_string(), rank: rank.saturating_sub(RANK_PENALTY) }
+        }).collect()
+    }
+
+    // Read the archives found in the volumes again if they changed, in the background
+    pub fn refresh(volumes: Vec<SharedVolume>) {
+        if REFRESHING.swap(true, Ordering::AcqRel) { return; } // the last refresh is still running
+
+        std::thread::spawn(move || {
+            let paths = volumes.iter()
+                .filter(|SharedVolume { building, .. }| !building.load(Ordering::Acquire))
+                .flat_map(|SharedVolume { volume, .. }| {
+                    let mut volume = volume.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    let paths = volume.paths_with_extensions(&ARCHIVE_EXTENSIONS);
+                    volume.end_borrow();
+                    paths
+                })
+                .collect::<HashSet<_>>();
+
+            // the archives are read without holding the lock, searches go on meanwhile
+            let stale = {
+                let mut archive_index = Self::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                let before = archive_index.archives.len();
+                archive_index.archives.retain(|path, _| paths.contains(path));
+                let removed = archive_index.archives.len() != before;
+                let stale = paths.into_iter().filter_map(|path| {
+                    let (size, modified) = file_stamp(&path)?;
+                    match archive_index.archives.get(&path) {
+                        Some(archive) if archive.size == size && archive.modified == modified => None,
+                        _ => Some((path, size, modified)),
+                    }
+                }).collect::<Vec<_>>();
+                if removed && stale.is_empty() {
+                    archive_index.save().unwrap_or_else(|e| log_util::log_error(format!("ArchiveIndex save error: {:?}", e)));
+                }
+                stale
+            };
+
+            if !stale.is_empty() {
+                let listed = stale.into_iter().filter_map(|(path, size, modified)| {
+                    match list_entries(&path) {
+                        Ok(entries) => Some((path, CachedArchive { size, modified, entries })),
+                        Err(e) => {
+                            log_util::log_error(format!("ArchiveIndex read {} error: {:?}", path, e));
+                            None
+                        }
+                    }
+                }).collect::<Vec<_>>();
+
+                let mut archive_index = Self::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                archive_index.archives.extend(listed);
+                archive_index.save().unwrap_or_else(|e| log_util::log_error(format!("ArchiveIndex save error: {:?}", e)));
+            }
+            REFRESHING.store(false, Ordering::Release);
+        });
+    }
+}
+
+fn file_stamp(path: &str) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_file() { return None; }
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    Some((metadata.len(), modified))
+}
+
+fn list_entries(path: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let archive = zip::ZipArchive::new(fs::File::open(path)?)?;
+    Ok(archive.file_names()
+        .filter(|name| !name.ends_with('/')) // folders
+        .take(MAX_ENTRIES)
+        .map(|name| name.to_string())
+        .collect())
+}
+
+// Whether a path points into an archive, told by the names only
+pub fn is_entry_path(full_path: &str) -> bool {
+    let mut components = full_path.split('\\').collect::<Vec<_>>();
+    components.pop(); // the name of the entry
+    components.iter().any(|component| {
+        component.rsplit_once('.').is_some_and(|(_, extension)| ARCHIVE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+    })
+}
+
+// The folder to show for a file, the one holding the archive for an entry of an archive
+pub fn containing_folder(full_path: &str) -> Option<String> {
+    let path = split_archive_path(full_path).map_or(full_path, |(archive_path, _)| archive_path);
+    Path::new(path).parent().map(|folder| folder.to_string_lossy().to_string())
+}
+
+// Split a path into the archive it lies in and the entry inside it, None for a plain file
+fn split_archive_path(full_path: &str) -> Option<(&str, &str)> {
+    let mut end = 0;
+    for component in full_path.split('\\') {
+        end += component.len();
+        let is_archive = component.rsplit_once('.')
+            .is_some_and(|(_, extension)| ARCHIVE_EXTENSIONS.contains(&extension.to_lowercase().as_str()));
+        if is_archive && end < full_path.len() && Path::new(&full_path[..end]).is_file() {
+            return Some((&full_path[..end], &full_path[end + 1..]));
+        }
+        end += 1; // the separator
+    }
+    None
+}
+
+// Extract an entry of an archive below the temp folder, the same entry is extracted again each time
+fn extract(archive_path: &str, entry: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let mut archive = zip::ZipArchive::new(fs::File::open(archive_path)?)?;
+    let zip_name = entry.replace('\\', "/");
+    let mut file = match archive.index_for_name(&zip_name) {
+        Some(index) => archive.by_index(index)?,
+        None => archive.by_name(entry)?, // archives written with backslashes
+    };
+    let enclosed_name = file.enclosed_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Unsafe entry name."))?;
+
+    let folder = format!("{:016x}", fxhash::hash64(archive_path));
+    let out_path = std::env::temp_dir().join("rotor").join("archives").join(folder).join(enclosed_name);
+    if let Some(parent) = out_path.parent() { fs::create_dir_all(parent)?; }
+    let mut out_file = fs::File::create(&out_path)?;
+    io::copy(&mut file, &mut out_file)?;
+    Ok(out_path)
+}
+
+// Open a search result, entries of archives are extracted first
+pub fn open(full_path: String) -> Result<(), Box<dyn Error>> {
+    match split_archive_path(&full_path) {
+        Some((archive_path, entry)) => {
+            let out_path = extract(archive_path, entry)?;
+            file_util::open_file(out_path.to_string_lossy().to_string())
+        },
+        None => file_util::open_file(full_path),
+    }
+}
+
+static INSTANCE: LazyLock<Mutex<ArchiveIndex>> = LazyLock::new(|| {
+    Mutex::new(ArchiveIndex::new())
+});
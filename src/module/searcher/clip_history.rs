@@ -0,0 +1,200 @@
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use arboard::Clipboard;
+use serde::{Serialize, Deserialize};
+
+use crate::core::application::app_config::AppConfig;
+use crate::util::{file_util, log_util, sys_util};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_TEXT_LEN: usize = 1 << 20; // bytes, larger copies are not kept
+const MAX_IMAGE_PIXELS: usize = 4096 * 4096;
+const MAX_TOTAL_TEXT: usize = 16 << 20; // bytes of text kept over all entries, the oldest go first
+const LOG_NAME: &str = "history.log"; // a json entry per line, appended on each copy
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum ClipContent {
+    Text(String),
+    Image { width: u32, height: u32 }, // saved as <hash>.png next to the history
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ClipEntry {
+    pub time: i64, // unix seconds of the last copy
+    pub hash: u64,
+    pub content: ClipContent,
+}
+
+impl ClipEntry {
+    pub fn image_path(&self) -> PathBuf {
+        ClipHistory::folder().join(format!("{:016x}.png", self.hash))
+    }
+
+    fn text_len(&self) -> usize {
+        match &self.content {
+            ClipContent::Text(text) => text.len(),
+            ClipContent::Image { .. } => 0,
+        }
+    }
+}
+
+// What was copied lately, newest first, recorded in the background and searched with "clip:" queries
+#[derive(Default)]
+pub struct ClipHistory {
+    entries: Vec<ClipEntry>,
+    log_size: u64, // bytes of the log, rewritten with only the kept entries once it doubles them
+}
+
+impl ClipHistory {
+    // Replay the log, a copy seen again moves up as it did while recording.
+    // Images whose file is gone are skipped, the limits are applied as if the entries were copied now
+    fn new() -> ClipHistory {
+        let Ok(text) = fs::read_to_string(Self::folder().join(LOG_NAME)) else { return ClipHistory::default(); };
+        let mut history = ClipHistory { entries: Vec::new(), log_size: text.len() as u64 };
+        for entry in text.lines().filter_map(|line| serde_json::from_str::<ClipEntry>(line).ok()) {
+            history.entries.retain(|e| e.hash != entry.hash);
+            history.entries.insert(0, entry);
+        }
+        history.entries.retain(|entry| !matches!(entry.content, ClipContent::Image { .. }) || entry.image_path().exists());
+        let max_entries = AppConfig::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get_clip_history_size() as usize;
+        history.trim(max_entries);
+        history
+    }
+
+    pub fn global() -> &'static Mutex<ClipHistory> {
+        &INSTANCE
+    }
+
+    fn folder() -> PathBuf {
+        file_util::get_userdata_path().join("clipboard")
+    }
+
+    // Append the newest entry, or write the kept entries anew when most of the log is dropped copies
+    fn save(&mut self) -> Result<(), Box<dyn Error>> {
+        let folder = Self::folder();
+        if !folder.exists() { fs::create_dir_all(&folder)?; }
+        let kept_size = self.entries.iter().map(|entry| entry.text_len() as u64 + 64).sum::<u64>();
+        if self.log_size > kept_size * 2 {
+            let mut text = String::new();
+            for entry in self.entries.iter().rev() {
+                text += &serde_json::to_string(entry)?;
+                text.push('\n');
+            }
+            fs::write(folder.join(LOG_NAME), &text)?;
+            self.log_size = text.len() as u64;
+            return Ok(());
+        }
+
+        let Some(newest) = self.entries.first() else { return Ok(()); };
+        let line = serde_json::to_string(newest)? + "\n";
+        OpenOptions::new().create(true).append(true).open(folder.join(LOG_NAME))?.write_all(line.as_bytes())?;
+        self.log_size += line.len() as u64;
+        Ok(())
+    }
+
+    // Forget every entry, with the saved images
+    pub fn clear(&mut self) -> Result<(), Box<dyn Error>> {
+        self.entries.clear();
+        self.log_size = 0;
+        let folder = Self::folder();
+        if folder.exists() { fs::remove_dir_all(&folder)?; }
+        Ok(())
+    }
+
+    // Entries whose text contains the filter, images only without a filter
+    pub fn search(&self, filter: &str, max: usize) -> Vec<ClipEntry> {
+        let filter = filter.trim().to_lowercase();
+        self.entries.iter()
+            .filter(|entry| match &entry.content {
+                ClipContent::Text(text) => filter.is_empty() || text.to_lowercase().contains(&filter),
+                ClipContent::Image { .. } => filter.is_empty(),
+            })
+            .take(max)
+            .cloned()
+            .collect()
+    }
+
+    // Put a copy on top, a copy seen before moves up instead of being kept twice
+    fn record(&mut self, entry: ClipEntry, max_entries: usize) -> Result<(), Box<dyn Error>> {
+        self.entries.retain(|e| e.hash != entry.hash);
+        self.entries.insert(0, entry);
+        self.trim(max_entries);
+        self.save()
+    }
+
+    // Keep the newest max_entries entries and no more than MAX_TOTAL_TEXT of text, the newest is always kept
+    fn trim(&mut self, max_entries: usize) {
+        let mut text_total = 0;
+        let kept = self.entries.iter()
+            .take_while(|e| { text_total += e.text_len(); text_total <= MAX_TOTAL_TEXT })
+            .count()
+            .clamp(1, max_entries.max(1));
+        for dropped in self.entries.drain(kept.min(self.entries.len())..) {
+            if let ClipContent::Image { .. } = dropped.content { let _ = fs::remove_file(dropped.image_path()); }
+        }
+    }
+
+    // Watch the clipboard for the whole session, recording only while enabled in the settings
+    pub fn start() {
+        std::thread::spawn(|| {
+            let mut last_sequence = sys_util::get_clipboard_sequence();
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                let sequence = sys_util::get_clipboard_sequence();
+                if sequence == last_sequence { continue; }
+                last_sequence = sequence;
+
+                let (enabled, max_entries, exclusions) = {
+                    let app_config = AppConfig::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    (app_config.get_clip_history(), app_config.get_clip_history_size() as usize, app_config.get_clip_exclusions())
+                };
+                if !enabled || sys_util::is_clipboard_private() { continue; }
+                let excluded = sys_util::get_clipboard_owner_process_name()
+                    .is_some_and(|name| exclusions.contains(&name.to_lowercase()));
+                if excluded { continue; }
+
+                match Self::read_clipboard() {
+                    Ok(Some(entry)) => {
+                        Self::global()
+                            .lock()
+                            .unwrap_or_else(|poisoned| poisoned.into_inner())
+                            .record(entry, max_entries)
+                            .unwrap_or_else(|e| log_util::log_error(format!("ClipHistory save error: {:?}", e)));
+                    },
+                    Ok(None) => {}, // files or other formats
+                    Err(e) => log_util::log_error(format!("ClipHistory read error: {:?}", e)),
+                }
+            }
+        });
+    }
+
+    fn read_clipboard() -> Result<Option<ClipEntry>, Box<dyn Error>> {
+        let mut clipboard = Clipboard::new()?;
+        let time = chrono::Local::now().timestamp();
+        if let Ok(text) = clipboard.get_text() {
+            if text.trim().is_empty() || text.len() > MAX_TEXT_LEN { return Ok(None); }
+            return Ok(Some(ClipEntry { time, hash: fxhash::hash64(&text), content: ClipContent::Text(text) }));
+        }
+
+        let Ok(image) = clipboard.get_image() else { return Ok(None); };
+        if image.width * image.height > MAX_IMAGE_PIXELS { return Ok(None); }
+        let (width, height) = (image.width as u32, image.height as u32);
+        let entry = ClipEntry { time, hash: fxhash::hash64(image.bytes.as_ref()), content: ClipContent::Image { width, height } };
+        let path = entry.image_path();
+        if !path.exists() {
+            fs::create_dir_all(Self::folder())?;
+            image::RgbaImage::from_raw(width, height, image.bytes.into_owned())
+                .ok_or("Image size does not match its data.")?
+                .save(&path)?;
+        }
+        Ok(Some(entry))
+    }
+}
+
+static INSTANCE: LazyLock<Mutex<ClipHistory>> = LazyLock::new(|| {
+    Mutex::new(ClipHistory::new())
+});
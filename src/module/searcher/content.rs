@@ -0,0 +1,252 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex, mpsc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::UNIX_EPOCH;
+use serde::{Serialize, Deserialize};
+
+use crate::core::application::app_config::AppConfig;
+use crate::util::{file_util, log_util};
+use super::file_data::SharedVolume;
+use super::volume::{ChangeLog, ChangeKind, SearchResultItem};
+
+pub const CONTENT_PREFIX: &str = "content:";
+const MAX_CANDIDATES: usize = 500; // files read to check the whole phrase
+
+static REFRESHING: AtomicBool = AtomicBool::new(false);
+
+// The words of a text file, read again only when its size or modification time changed
+#[derive(Serialize, Deserialize)]
+struct Document {
+    path: String,
+    size: u64,
+    modified: u64, // unix seconds
+    words: Vec<String>, // unique and lowercased
+}
+
+// Which files are indexed, taken from the config for every refresh
+#[derive(Clone, PartialEq)]
+struct ContentFilter {
+    extensions: Vec<String>, // lowercased, without the dot
+    max_size: u64, // bytes
+}
+
+impl ContentFilter {
+    fn from_config() -> ContentFilter {
+        let app_config = AppConfig::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        ContentFilter {
+            extensions: app_config.get_content_extensions(),
+            max_size: app_config.get_content_max_kb() as u64 * 1024,
+        }
+    }
+
+    fn accepts_name(&self, path: &str) -> bool {
+        path.rsplit_once('.')
+            .is_some_and(|(_, extension)| self.extensions.contains(&extension.to_lowercase()))
+    }
+}
+
+// An inverted index of the words in small text files, searched with "content:" queries
+#[derive(Default, Serialize, Deserialize)]
+pub struct ContentIndex {
+    documents: Vec<Document>,
+    #[serde(skip)]
+    postings: HashMap<String, Vec<u32>>, // word -> positions in documents
+    #[serde(skip)]
+    built_with: Option<ContentFilter>, // all volumes were scanned with it in this session, changes are applied from then on
+}
+
+impl ContentIndex {
+    fn new() -> ContentIndex {
+        let mut content_index: ContentIndex = fs::read_to_string(Self::cache_path())
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        content_index.rebuild_postings();
+        content_index
+    }
+
+    pub fn global() -> &'static Mutex<ContentIndex> {
+        &INSTANCE
+    }
+
+    fn cache_path() -> PathBuf {
+        file_util::get_userdata_path().join("content.json")
+    }
+
+    fn save(&self) -> Result<(), Box<dyn Error>> {
+        let root_path = file_util::get_userdata_path();
+        if !root_path.exists() { fs::create_dir_all(&root_path)?; }
+        fs::write(Self::cache_path(), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn rebuild_postings(&mut self) {
+        self.postings.clear();
+        for (position, document) in self.documents.iter().enumerate() {
+            for word in &document.words {
+                self.postings.entry(word.clone()).or_default().push(position as u32);
+            }
+        }
+    }
+
+    // Files containing the phrase of a query, the ones containing it most often first. The files are read
+    // without holding the index, None if stopped
+    pub fn search(phrase: &str, max: usize, stop_receiver: &mpsc::Receiver<()>) -> Option<Vec<SearchResultItem>> {
+        let phrase = phrase.trim().to_lowercase();
+        let candidates = Self::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).candidates(&phrase);
+
+        let mut found = Vec::new();
+        for path in candidates {
+            if stop_receiver.try_recv().is_ok() { return None; }
+            let Ok(text) = fs::read_to_string(&path) else { continue; };
+            let count = text.to_lowercase().matches(phrase.as_str()).count();
+            if count == 0 { continue; }
+            let Some((path, file_name)) = path.rsplit_once('\\') else { continue; };
+            found.push((count, SearchResultItem { path: path.to_string() + "\\", file_name: file_name.to_string(), rank: count.min(i8::MAX as usize) as i8 }));
+        }
+        found.sort_by_key(|(count, _)| std::cmp::Reverse(*count));
+        Some(found.into_iter().take(max).map(|(_, item)| item).collect())
+    }
+
+    // The paths of the files with all words of the lowercased phrase, at most MAX_CANDIDATES
+    fn candidates(&self, phrase: &str) -> Vec<String> {
+        let words = split_words(phrase);
+        if words.is_empty() { return Vec::new(); }
+
+        // documents with all words of the phrase, starting from the rarest word
+        let mut lists = words.iter().map(|word| self.postings.get(word).map(Vec::as_slice).unwrap_or_default()).collect::<Vec<_>>();
+        lists.sort_by_key(|list| list.len());
+        let mut candidates = lists[0].to_vec();
+        for list in &lists[1..] {
+            candidates.retain(|position| list.binary_search(position).is_ok());
+        }
+        candidates.truncate(MAX_CANDIDATES);
+        candidates.into_iter().map(|position| self.documents[position as usize].path.clone()).collect()
+    }
+
+    // Index the text files of the volumes in the background, the first time in a session all of them
+    // and afterwards the ones the volumes reported as changed
+    pub fn refresh(volumes: Vec<SharedVolume>) {
+        if REFRESHING.swap(true, Ordering::AcqRel) { return; } // the last refresh is still running
+
+        std::thread::spawn(move || {
+            let filter = ContentFilter::from_config();
+            let built = Self::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).built_with.as_ref() == Some(&filter);
+            let pending = ChangeLog::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take_pending();
+
+            match pending {
+                Some(events) if built => {
+                    let mut changed = HashSet::new();
+                    let mut removed = HashSet::new();
+                    for event in events {
+                        if event.kind == ChangeKind::Renamed || event.kind == ChangeKind::Deleted {
+                            let old_path = if event.kind == ChangeKind::Renamed { event.old_path } else { event.path.clone() };
+                            changed.remove(&old_path);
+                            removed.insert(old_path);
+                        }
+                        if event.kind != ChangeKind::Deleted && filter.accepts_name(&event.path) {
+                            removed.remove(&event.path);
+                            changed.insert(event.path);
+                        }
+                    }
+                    Self::apply(&filter, changed.into_iter().collect(), removed, false);
+                },
+                _ => { // the first refresh, or changes were missed
+                    let extensions = filter.extensions.iter().map(String::as_str).collect::<Vec<_>>();
+                    let paths = volumes.iter()
+                        .filter(|SharedVolume { building, .. }| !building.load(Ordering::Acquire))
+                        .flat_map(|SharedVolume { volume, .. }| {
+                            let mut volume = volume.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                            let paths = volume.paths_with_extensions(&extensions);
+                            volume.end_borrow();
+                            paths
+                        })
+                        .collect::<Vec<_>>();
+                    Self::apply(&filter, paths, HashSet::new(), true);
+                },
+            }
+            REFRESHING.store(false, Ordering::Release);
+        });
+    }
+
+    // Read the given files if they changed and drop the removed ones, or all others for a full scan
+    fn apply(filter: &ContentFilter, paths: Vec<String>, mut removed: HashSet<String>, full: bool) {
+        // the files are read without holding the lock, searches go on meanwhile
+        let stale = {
+            let content_index = Self::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let stamps = content_index.documents.iter()
+                .map(|document| (document.path.as_str(), (document.size, document.modified)))
+                .collect::<HashMap<_, _>>();
+            paths.iter().filter_map(|path| {
+                match file_stamp(path).filter(|(size, _)| *size <= filter.max_size) {
+                    Some(stamp) if stamps.get(path.as_str()) == Some(&stamp) => None,
+                    Some(stamp) => Some((path.clone(), stamp)),
+                    None => { // gone or grown too large
+                        removed.insert(path.clone());
+                        None
+                    },
+                }
+            }).collect::<Vec<_>>()
+        };
+        let read = stale.into_iter().filter_map(|(path, (size, modified))| {
+            let Ok(text) = fs::read_to_string(&path) else { // not utf8, not a text file after all
+                removed.insert(path);
+                return None;
+            };
+            let mut words = split_words(&text.to_lowercase());
+            words.sort();
+            words.dedup();
+            Some(Document { path, size, modified, words })
+        }).collect::<Vec<_>>();
+
+        let kept = paths.iter().collect::<HashSet<_>>();
+        let read_paths = read.iter().map(|document| document.path.clone()).collect::<HashSet<_>>();
+        let mut content_index = Self::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let before = content_index.documents.len();
+        content_index.documents.retain(|document| {
+            !removed.contains(&document.path)
+                && !read_paths.contains(&document.path)
+                && (!full || kept.contains(&document.path))
+                && filter.accepts_name(&document.path) // the extensions may have changed
+        });
+        let changed = !read.is_empty() || content_index.documents.len() != before;
+        content_index.documents.extend(read);
+        if full { content_index.built_with = Some(filter.clone()); }
+
+        if changed {
+            content_index.rebuild_postings();
+            content_index.save().unwrap_or_else(|e| log_util::log_error(format!("ContentIndex save error: {:?}", e)));
+        }
+    }
+}
+
+fn file_stamp(path: &str) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_file() { return None; }
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    Some((metadata.len(), modified))
+}
+
+// Runs of letters and digits are words, except for east asian scripts without spaces between words,
+// where every character is a word of its own
+fn split_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() && (c as u32) < 0x2E80 { // below the CJK radicals
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() { words.push(std::mem::take(&mut word)); }
+        if c.is_alphanumeric() { words.push(c.to_string()); }
+    }
+    if !word.is_empty() { words.push(word); }
+    words
+}
+
+static INSTANCE: LazyLock<Mutex<ContentIndex>> = LazyLock::new(|| {
+    Mutex::new(ContentIndex::new())
+});
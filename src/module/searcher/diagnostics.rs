@@ -0,0 +1,163 @@
+use std::error::Error;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use arboard::Clipboard;
+use slint::{ComponentHandle, Model, VecModel};
+use i_slint_backend_winit::WinitWindowAccessor;
+
+use crate::core::application::app_config::AppConfig;
+use crate::ui::{DiagnosticsWindow, VolumeDiagnostics_slint};
+use crate::util::{file_util, log_util};
+use super::file_data::SharedVolume;
+use super::volume::VolumeDiagnostics;
+
+const NONE_TEXT: &str = "-";
+
+// Shows the index statistics and timings of every volume, to triage slow searches
+pub struct Diagnostics {
+    pub diagnostics_win: DiagnosticsWindow,
+}
+
+impl Diagnostics {
+    pub fn new(volumes: Arc<Mutex<Vec<SharedVolume>>>) -> Result<Diagnostics, Box<dyn Error>> {
+        let diagnostics_win = DiagnosticsWindow::new()?;
+        {
+            let mut app_config = AppConfig::global().lock()?;
+            diagnostics_win.invoke_change_theme(app_config.get_theme() as i32);
+            app_config.diagnostics_win = Some(diagnostics_win.as_weak());
+        }
+        diagnostics_win.set_volumes(Rc::new(VecModel::<VolumeDiagnostics_slint>::default()).into());
+
+        { // refresh
+            let diagnostics_win_clone = diagnostics_win.as_weak();
+            diagnostics_win.on_refresh(move || {
+                if let Some(diagnostics_win) = diagnostics_win_clone.upgrade() {
+                    diagnostics_win.set_collecting(true);
+                }
+                let (volumes, diagnostics_win) = (volumes.clone(), diagnostics_win_clone.clone());
+                thread::spawn(move || {
+                    let rows = Self::collect(&volumes);
+                    diagnostics_win.upgrade_in_event_loop(move |diagnostics_win| {
+                        diagnostics_win.set_volumes(Rc::new(VecModel::from(rows)).into());
+                        diagnostics_win.set_collecting(false);
+                    }).unwrap_or_else(|e| log_util::log_error(format!("Diagnostics publish: {:?}", e)));
+                });
+            });
+        }
+
+        { // copy report
+            let diagnostics_win_clone = diagnostics_win.as_weak();
+            diagnostics_win.on_copy_report(move || {
+                if let Some(diagnostics_win) = diagnostics_win_clone.upgrade() {
+                    let report = Self::report(diagnostics_win.get_volumes().iter());
+                    Clipboard::new()
+                        .and_then(|mut clipboard| clipboard.set_text(report))
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to copy diagnostics report: {:?}", e)));
+                }
+            });
+        }
+
+        { // minimize, close, win move
+            let diagnostics_win_clone = diagnostics_win.as_weak();
+            diagnostics_win.on_minimize(move || {
+                if let Some(diagnostics_win) = diagnostics_win_clone.upgrade() {
+                    diagnostics_win.window().with_winit_window(|winit_win| {
+                        winit_win.set_minimized(true);
+                    });
+                }
+            });
+
+            let diagnostics_win_clone = diagnostics_win.as_weak();
+            diagnostics_win.on_close(move || {
+                if let Some(diagnostics_win) = diagnostics_win_clone.upgrade() {
+                    let _ = diagnostics_win.hide();
+                }
+            });
+
+            let diagnostics_win_clone = diagnostics_win.as_weak();
+            diagnostics_win.on_win_move(move || {
+                if let Some(diagnostics_win) = diagnostics_win_clone.upgrade() {
+                    diagnostics_win.window().with_winit_window(|winit_win| {
+                        winit_win.drag_window().unwrap_or_else(
+                            |e| log_util::log_error(format!("Failed to drag window: {:?}", e))
+                        );
+                    });
+                }
+            });
+        }
+
+        Ok(Diagnostics { diagnostics_win })
+    }
+
+    fn collect(volumes: &Mutex<Vec<SharedVolume>>) -> Vec<VolumeDiagnostics_slint> {
+        let volumes = volumes.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        volumes.into_iter().map(|SharedVolume { volume, building, progress }| {
+            if building.load(Ordering::Acquire) {
+                // the build holds the volume, only its name can be read
+                return VolumeDiagnostics_slint {
+                    name: progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).name.clone().into(),
+                    building: true,
+                    ..Default::default()
+                };
+            }
+            let diagnostics = volume.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).diagnostics();
+            Self::to_row(diagnostics)
+        }).collect()
+    }
+
+    fn to_row(diagnostics: VolumeDiagnostics) -> VolumeDiagnostics_slint {
+        let VolumeDiagnostics { name, loaded, entry_count, fd_size, memory_size, last_build, metrics, start_usn, journal_id, top_extensions } = diagnostics;
+        let extensions = top_extensions.iter()
+            .map(|(extension, count)| format!("{} {}", extension, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        VolumeDiagnostics_slint {
+            name: name.into(),
+            building: false,
+            loaded,
+            entries: entry_count as i32,
+            fd_size: fd_size.map(file_util::format_size).unwrap_or(NONE_TEXT.into()).into(),
+            memory_size: if loaded { file_util::format_size(memory_size as u64) } else { NONE_TEXT.into() }.into(),
+            last_build: last_build.map(format_time).unwrap_or(NONE_TEXT.into()).into(),
+            build_time: format_duration(metrics.build).into(),
+            update_time: format_duration(metrics.update).into(),
+            update_records: metrics.update_records as i32,
+            read_time: format_duration(metrics.read).into(),
+            write_time: format_duration(metrics.write).into(),
+            find_time: format_duration(metrics.find).into(),
+            start_usn: if journal_id.is_some() { start_usn.to_string() } else { NONE_TEXT.into() }.into(),
+            journal_id: journal_id.map(|id| format!("{:#x}", id)).unwrap_or(NONE_TEXT.into()).into(),
+            extensions: if extensions.is_empty() { NONE_TEXT.into() } else { extensions }.into(),
+        }
+    }
+
+    // plain text for bug reports, the labels are not translated
+    fn report(rows: impl Iterator<Item = VolumeDiagnostics_slint>) -> String {
+        let mut report = format!("Rotor {} index diagnostics\r\n", option_env!("CARGO_PKG_VERSION").unwrap_or("unknown"));
+        for row in rows {
+            report += &format!("\r\n[{}]\r\n", row.name);
+            if row.building {
+                report += "building\r\n";
+                continue;
+            }
+            report += &format!(
+                "loaded: {}\r\nentries: {}\r\nfd size: {}\r\nmemory: {}\r\nlast build: {}\r\nbuild: {}\r\nupdate: {} ({} records)\r\nread: {}\r\nwrite: {}\r\nfind: {}\r\nstart_usn: {}\r\njournal id: {}\r\nextensions: {}\r\n",
+                row.loaded, row.entries, row.fd_size, row.memory_size, row.last_build, row.build_time,
+                row.update_time, row.update_records, row.read_time, row.write_time, row.find_time,
+                row.start_usn, row.journal_id, row.extensions
+            );
+        }
+        report
+    }
+}
+
+fn format_duration(duration: Option<Duration>) -> String {
+    duration.map(|duration| format!("{} ms", duration.as_millis())).unwrap_or(NONE_TEXT.into())
+}
+
+fn format_time(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Local>::from(time).format("%Y-%m-%d %H:%M:%S").to_string()
+}
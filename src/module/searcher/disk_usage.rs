@@ -0,0 +1,210 @@
+use std::error::Error;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
+use std::thread;
+use slint::{ComponentHandle, SharedString, VecModel};
+use i_slint_backend_winit::WinitWindowAccessor;
+
+use crate::core::application::app_config::AppConfig;
+use crate::ui::{DiskUsageWindow, UsageRow_slint, UsageStatus};
+use crate::util::{file_util, log_util};
+use super::file_data::SharedVolume;
+use super::volume::UsageEntry;
+
+const MAX_ROWS: usize = 500; // the largest children of a folder that are shown
+
+// Where the view is: the volume and the folders opened from its root
+#[derive(Default)]
+struct UsageState {
+    volumes: Vec<SharedVolume>, // taken when the window is shown
+    volume: usize,
+    folders: Vec<(u64, String)>, // index and name, the root of the volume is not in it
+    children: Vec<UsageEntry>, // of the folder shown, in the order of the rows
+}
+
+// Shows the largest folders and files of a volume, summed up from the index
+pub struct DiskUsage {
+    pub disk_usage_win: DiskUsageWindow,
+    volumes: Arc<Mutex<Vec<SharedVolume>>>,
+    state: Arc<Mutex<UsageState>>,
+}
+
+impl DiskUsage {
+    pub fn new(volumes: Arc<Mutex<Vec<SharedVolume>>>) -> Result<DiskUsage, Box<dyn Error>> {
+        let disk_usage_win = DiskUsageWindow::new()?;
+        {
+            let mut app_config = AppConfig::global().lock()?;
+            disk_usage_win.invoke_change_theme(app_config.get_theme() as i32);
+            app_config.disk_usage_win = Some(disk_usage_win.as_weak());
+        }
+
+        let state = Arc::new(Mutex::new(UsageState::default()));
+
+        { // select volume
+            let disk_usage_win_clone = disk_usage_win.as_weak();
+            let state = state.clone();
+            disk_usage_win.on_select_volume(move |volume| {
+                let left = {
+                    let mut state = state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    let left = state.volumes.get(state.volume).cloned();
+                    state.volume = volume as usize;
+                    state.folders.clear();
+                    left
+                };
+                if let Some(SharedVolume { volume, .. }) = left {
+                    volume.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).end_borrow();
+                }
+                Self::load(&state, &disk_usage_win_clone);
+            });
+        }
+
+        { // open folder
+            let disk_usage_win_clone = disk_usage_win.as_weak();
+            let state = state.clone();
+            disk_usage_win.on_open_folder(move |id| {
+                {
+                    let mut state = state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    let Some(child) = state.children.get(id as usize) else { return; };
+                    let folder = (child.index, child.name.clone());
+                    state.folders.push(folder);
+                }
+                Self::load(&state, &disk_usage_win_clone);
+            });
+        }
+
+        { // go up
+            let disk_usage_win_clone = disk_usage_win.as_weak();
+            let state = state.clone();
+            disk_usage_win.on_go_up(move || {
+                state.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).folders.pop();
+                Self::load(&state, &disk_usage_win_clone);
+            });
+        }
+
+        { // refresh
+            let disk_usage_win_clone = disk_usage_win.as_weak();
+            let state = state.clone();
+            disk_usage_win.on_refresh(move || {
+                Self::load(&state, &disk_usage_win_clone);
+            });
+        }
+
+        { // minimize, close, win move
+            let disk_usage_win_clone = disk_usage_win.as_weak();
+            disk_usage_win.on_minimize(move || {
+                if let Some(disk_usage_win) = disk_usage_win_clone.upgrade() {
+                    disk_usage_win.window().with_winit_window(|winit_win| {
+                        winit_win.set_minimized(true);
+                    });
+                }
+            });
+
+            let disk_usage_win_clone = disk_usage_win.as_weak();
+            let state = state.clone();
+            disk_usage_win.on_close(move || {
+                if let Some(disk_usage_win) = disk_usage_win_clone.upgrade() {
+                    let _ = disk_usage_win.hide();
+                }
+                // the indexes read only for this view are released again
+                let volumes = std::mem::take(&mut *state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())).volumes;
+                for SharedVolume { volume, .. } in volumes {
+                    volume.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).end_borrow();
+                }
+            });
+
+            let disk_usage_win_clone = disk_usage_win.as_weak();
+            disk_usage_win.on_win_move(move || {
+                if let Some(disk_usage_win) = disk_usage_win_clone.upgrade() {
+                    disk_usage_win.window().with_winit_window(|winit_win| {
+                        winit_win.drag_window().unwrap_or_else(
+                            |e| log_util::log_error(format!("Failed to drag window: {:?}", e))
+                        );
+                    });
+                }
+            });
+        }
+
+        Ok(DiskUsage { disk_usage_win, volumes, state })
+    }
+
+    // Show the window with the current volumes, starting at the root of the first one
+    pub fn show(&self) {
+        let volumes = self.volumes.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        let names = volumes.iter()
+            .map(|SharedVolume { progress, .. }| {
+                SharedString::from(progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).name.clone())
+            })
+            .collect::<Vec<_>>();
+        *self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = UsageState { volumes, ..Default::default() };
+
+        self.disk_usage_win.set_volumes(Rc::new(VecModel::from(names)).into());
+        self.disk_usage_win.set_volume_index(0);
+        let _ = self.disk_usage_win.show();
+        Self::load(&self.state, &self.disk_usage_win.as_weak());
+    }
+
+    // Read the children of the current folder in the background
+    fn load(state: &Arc<Mutex<UsageState>>, disk_usage_win: &slint::Weak<DiskUsageWindow>) {
+        if let Some(disk_usage_win) = disk_usage_win.upgrade() {
+            disk_usage_win.set_status(UsageStatus::Loading);
+        }
+
+        let (state, disk_usage_win) = (state.clone(), disk_usage_win.clone());
+        thread::spawn(move || {
+            let (shared_volume, folder) = {
+                let state = state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                (state.volumes.get(state.volume).cloned(), state.folders.last().map(|(index, _)| *index))
+            };
+            let Some(SharedVolume { volume, building, progress }) = shared_volume else {
+                Self::publish(&disk_usage_win, UsageStatus::Idle, Vec::new(), 0, String::new(), false, 0);
+                return;
+            };
+            if building.load(Ordering::Acquire) {
+                Self::publish(&disk_usage_win, UsageStatus::Building, Vec::new(), 0, String::new(), false, 0);
+                return;
+            }
+
+            let (children, size) = volume.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).usage_children(folder);
+            let rows = children.iter().take(MAX_ROWS).enumerate().map(|(id, child)| UsageRow_slint {
+                id: id as i32,
+                name: child.name.as_str().into(),
+                size: file_util::format_size(child.size).into(),
+                ratio: if size > 0 { (child.size as f64 / size as f64) as f32 } else { 0. },
+                is_folder: child.is_folder,
+            }).collect::<Vec<_>>();
+            let hidden_rows = children.len().saturating_sub(MAX_ROWS);
+
+            let path = {
+                let mut state = state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                state.children = children;
+                let root = progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).name.clone();
+                std::iter::once(root.trim_end_matches('\\').to_string())
+                    .chain(state.folders.iter().map(|(_, name)| name.clone()))
+                    .collect::<Vec<_>>()
+                    .join("\\")
+            };
+            let can_go_up = folder.is_some();
+            Self::publish(&disk_usage_win, UsageStatus::Idle, rows, hidden_rows, path + "\\", can_go_up, size);
+        });
+    }
+
+    fn publish(
+        disk_usage_win: &slint::Weak<DiskUsageWindow>,
+        status: UsageStatus,
+        rows: Vec<UsageRow_slint>,
+        hidden_rows: usize,
+        path: String,
+        can_go_up: bool,
+        size: u64
+    ) {
+        disk_usage_win.upgrade_in_event_loop(move |disk_usage_win| {
+            disk_usage_win.set_rows(Rc::new(VecModel::from(rows)).into());
+            disk_usage_win.set_hidden_rows(hidden_rows as i32);
+            disk_usage_win.set_current_path(path.into());
+            disk_usage_win.set_current_size(file_util::format_size(size).into());
+            disk_usage_win.set_can_go_up(can_go_up);
+            disk_usage_win.set_status(status);
+        }).unwrap_or_else(|e| log_util::log_error(format!("DiskUsage publish: {:?}", e)));
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error;
+use std::fs;
+use std::hash::Hasher;
+use std::io::Read;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use slint::{ComponentHandle, Model, VecModel};
+use i_slint_backend_winit::WinitWindowAccessor;
+
+use crate::core::application::app_config::AppConfig;
+use crate::ui::{DuplicateWindow, DuplicateRow_slint, DuplicateState};
+use crate::util::{file_util, log_util};
+use super::file_data::SharedVolume;
+
+const MAX_GROUPS: usize = 1000; // common names like index.js would flood the view
+const MAX_NAME_FILES: usize = 100; // names shared by more files are left out, for the same reason
+const MAX_STATS: usize = 50_000; // files whose size is read before the groups are picked
+
+struct Candidate {
+    path: String,
+    size: u64,
+}
+
+struct CandidateGroup {
+    name: String,
+    files: Vec<Candidate>,
+    first_row: usize,
+}
+
+// Finds files with the same name (and size) across all volumes, confirmed by hashing and comparing their content
+pub struct DuplicateFinder {
+    pub duplicate_win: DuplicateWindow,
+}
+
+impl DuplicateFinder {
+    pub fn new(volumes: Arc<Mutex<Vec<SharedVolume>>>) -> Result<DuplicateFinder, Box<dyn Error>> {
+        let duplicate_win = DuplicateWindow::new()?;
+        {
+            let mut app_config = AppConfig::global().lock()?;
+            duplicate_win.invoke_change_theme(app_config.get_theme() as i32);
+            app_config.duplicate_win = Some(duplicate_win.as_weak());
+        }
+        duplicate_win.set_rows(Rc::new(VecModel::<DuplicateRow_slint>::default()).into());
+
+        let scan_id = Arc::new(AtomicUsize::new(0)); // bumped to stop the running scan
+
+        { // start
+            let duplicate_win_clone = duplicate_win.as_weak();
+            let scan_id = scan_id.clone();
+            duplicate_win.on_start(move |match_size| {
+                let id = scan_id.fetch_add(1, Ordering::AcqRel) + 1;
+                if let Some(duplicate_win) = duplicate_win_clone.upgrade() {
+                    duplicate_win.set_scanning(true);
+                    duplicate_win.set_candidate_groups(0);
+                    duplicate_win.set_checked_groups(0);
+                    duplicate_win.set_duplicate_groups(0);
+                    duplicate_win.set_rows(Rc::new(VecModel::<DuplicateRow_slint>::default()).into());
+                }
+
+                let (volumes, scan_id, duplicate_win) = (volumes.clone(), scan_id.clone(), duplicate_win_clone.clone());
+                thread::spawn(move || {
+                    Self::scan(&volumes, match_size, id, &scan_id, &duplicate_win);
+                });
+            });
+        }
+
+        { // file actions
+            let duplicate_win_clone = duplicate_win.as_weak();
+            duplicate_win.on_open_file(move |id| {
+                if let Some(path) = Self::row_path(&duplicate_win_clone, id) {
+                    file_util::open_file(path)
+                        .unwrap_or_else(|e| log_util::log_error(format!("open_file error: {:?}", e)));
+                }
+            });
+
+            let duplicate_win_clone = duplicate_win.as_weak();
+            duplicate_win.on_reveal_file(move |id| {
+                if let Some(path) = Self::row_path(&duplicate_win_clone, id) {
+                    if let Some(folder) = Path::new(&path).parent() {
+                        file_util::open_file(folder.to_string_lossy().to_string())
+                            .unwrap_or_else(|e| log_util::log_error(format!("open_file error: {:?}", e)));
+                    }
+                }
+            });
+
+            let duplicate_win_clone = duplicate_win.as_weak();
+            duplicate_win.on_delete_file(move |id| {
+                if let Some(duplicate_win) = duplicate_win_clone.upgrade() {
+                    Self::delete_copy(&duplicate_win, id as usize);
+                }
+            });
+        }
+
+        { // minimize, close, win move
+            let duplicate_win_clone = duplicate_win.as_weak();
+            duplicate_win.on_minimize(move || {
+                if let Some(duplicate_win) = duplicate_win_clone.upgrade() {
+                    duplicate_win.window().with_winit_window(|winit_win| {
+                        winit_win.set_minimized(true);
+                    });
+                }
+            });
+
+            let duplicate_win_clone = duplicate_win.as_weak();
+            duplicate_win.on_close(move || {
+                scan_id.fetch_add(1, Ordering::AcqRel);
+                if let Some(duplicate_win) = duplicate_win_clone.upgrade() {
+                    duplicate_win.set_scanning(false);
+                    let _ = duplicate_win.hide();
+                }
+            });
+
+            let duplicate_win_clone = duplicate_win.as_weak();
+            duplicate_win.on_win_move(move || {
+                if let Some(duplicate_win) = duplicate_win_clone.upgrade() {
+                    duplicate_win.window().with_winit_window(|winit_win| {
+                        winit_win.drag_window().unwrap_or_else(
+                            |e| log_util::log_error(format!("Failed to drag window: {:?}", e))
+                        );
+                    });
+                }
+            });
+        }
+
+        Ok(DuplicateFinder { duplicate_win })
+    }
+
+    fn row_path(duplicate_win: &slint::Weak<DuplicateWindow>, id: i32) -> Option<String> {
+        let row = duplicate_win.upgrade()?.get_rows().row_data(id as usize)?;
+        if row.header { None } else { Some(row.path.to_string()) }
+    }
+
+    // Move a file to the recycle bin if another file of its group still has the same content,
+    // the last remaining copy is unique then
+    fn delete_copy(duplicate_win: &DuplicateWindow, id: usize) {
+        let rows = duplicate_win.get_rows();
+        let Some(row) = rows.row_data(id) else { return; };
+        if row.header || row.state != DuplicateState::Duplicate { return; }
+
+        // the rows of the group, between its header and the next one
+        let first = (0..id).rev().find(|&index| rows.row_data(index).is_some_and(|row| row.header)).map_or(0, |index| index + 1);
+        let end = (id + 1..rows.row_count()).find(|&index| rows.row_data(index).is_some_and(|row| row.header)).unwrap_or(rows.row_count());
+        let copies = (first..end)
+            .filter(|&index| index != id)
+            .filter(|&index| rows.row_data(index).is_some_and(|other| other.state == DuplicateState::Duplicate && other.content == row.content))
+            .collect::<Vec<_>>();
+        if copies.is_empty() { return; }
+
+        if let Err(e) = file_util::move_to_recycle_bin(&row.path) {
+            log_util::log_error(format!("Failed to delete {}: {:?}", row.path, e));
+            return;
+        }
+        rows.set_row_data(id, DuplicateRow_slint { state: DuplicateState::Deleted, ..row });
+        if let [last] = copies.as_slice() {
+            if let Some(other) = rows.row_data(*last) {
+                rows.set_row_data(*last, DuplicateRow_slint { state: DuplicateState::Unique, ..other });
+            }
+        }
+    }
+
+    fn scan(
+        volumes: &Mutex<Vec<SharedVolume>>,
+        match_size: bool,
+        id: usize,
+        scan_id: &AtomicUsize,
+        duplicate_win: &slint::Weak<DuplicateWindow>
+    ) {
+        let stopped = || scan_id.load(Ordering::Acquire) != id;
+
+        let Some(mut groups) = Self::collect_groups(volumes, match_size, &stopped) else { return; };
+
+        // rows of a group: a header followed by its files
+        let mut rows = Vec::new();
+        for group in &mut groups {
+            group.first_row = rows.len();
+            rows.push(DuplicateRow_slint {
+                id: rows.len() as i32,
+                header: true,
+                name: group.name.clone().into(),
+                count: group.files.len() as i32,
+                ..Default::default()
+            });
+            for file in &group.files {
+                rows.push(DuplicateRow_slint {
+                    id: rows.len() as i32,
+                    name: group.name.clone().into(),
+                    path: file.path.clone().into(),
+                    size: file_util::format_size(file.size).into(),
+                    ..Default::default()
+                });
+            }
+        }
+        let candidate_groups = groups.len() as i32;
+        duplicate_win.upgrade_in_event_loop(move |duplicate_win| {
+            duplicate_win.set_candidate_groups(candidate_groups);
+            duplicate_win.set_rows(Rc::new(VecModel::from(rows)).into());
+        }).unwrap_or_else(|e| log_util::log_error(format!("DuplicateFinder publish rows: {:?}", e)));
+
+        let (mut checked_groups, mut duplicate_groups) = (0, 0);
+        for group in groups {
+            let Some(mut states) = Self::confirm_group(&group, &stopped) else { return; };
+
+            let is_duplicate = states.iter().any(|(state, _)| *state == DuplicateState::Duplicate);
+            states.insert(0, (if is_duplicate { DuplicateState::Duplicate } else { DuplicateState::Unique }, 0));
+            checked_groups += 1;
+            if is_duplicate { duplicate_groups += 1; }
+
+            let first_row = group.first_row;
+            duplicate_win.upgrade_in_event_loop(move |duplicate_win| {
+                let rows = duplicate_win.get_rows();
+                for (offset, (state, content)) in states.into_iter().enumerate() {
+                    if let Some(mut row) = rows.row_data(first_row + offset) {
+                        if row.state == DuplicateState::Deleted { continue; }
+                        row.state = state;
+                        row.content = content;
+                        rows.set_row_data(first_row + offset, row);
+                    }
+                }
+                duplicate_win.set_checked_groups(checked_groups);
+                duplicate_win.set_duplicate_groups(duplicate_groups);
+            }).unwrap_or_else(|e| log_util::log_error(format!("DuplicateFinder publish group: {:?}", e)));
+        }
+
+        duplicate_win.upgrade_in_event_loop(|duplicate_win| {
+            duplicate_win.set_scanning(false);
+        }).unwrap_or_else(|e| log_util::log_error(format!("DuplicateFinder finish: {:?}", e)));
+    }
+
+    // Group the files of all volumes by name, and by size if asked, keeping groups of two or more. None if stopped
+    fn collect_groups(volumes: &Mutex<Vec<SharedVolume>>, match_size: bool, stopped: &dyn Fn() -> bool) -> Option<Vec<CandidateGroup>> {
+        let volumes = volumes.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        let volumes = volumes.into_iter()
+            .filter(|SharedVolume { building, .. }| !building.load(Ordering::Acquire))
+            .collect::<Vec<_>>();
+
+        let mut names: HashMap<String, Vec<(usize, u64)>> = HashMap::new(); // name -> (volume, index)
+        for (volume_index, SharedVolume { volume, .. }) in volumes.iter().enumerate() {
+            let name_groups = volume.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).name_groups();
+            for (name, indexes) in name_groups {
+                names.entry(name).or_default().extend(indexes.into_iter().map(|index| (volume_index, index)));
+            }
+        }
+        names.retain(|_, files| files.len() > 1 && files.len() <= MAX_NAME_FILES);
+        if stopped() { return None; }
+
+        // the paths of every volume in one go, then its index is released again if it was read only for this
+        let mut full_paths: HashMap<(usize, u64), String> = HashMap::new();
+        for (volume_index, SharedVolume { volume, .. }) in volumes.iter().enumerate() {
+            let indexes = names.values().flatten().filter(|(v, _)| *v == volume_index).map(|(_, index)| *index).collect::<Vec<_>>();
+            let mut volume = volume.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if !indexes.is_empty() {
+                full_paths.extend(volume.full_paths(&indexes).into_iter().map(|(index, path)| ((volume_index, index), path)));
+            }
+            volume.end_borrow();
+        }
+
+        let mut groups = Vec::new();
+        let mut stats = 0;
+        for files in names.into_values() {
+            if stats >= MAX_STATS { break; }
+            if stopped() { return None; }
+            let paths = files.iter().filter_map(|file| full_paths.remove(file)).collect::<Vec<_>>();
+            stats += paths.len();
+
+            // files that were removed since the last index update are left out
+            let files = paths.into_iter().filter_map(|path| {
+                let metadata = fs::metadata(&path).ok().filter(|metadata| metadata.is_file())?;
+                Some(Candidate { path, size: metadata.len() })
+            }).collect::<Vec<_>>();
+            let name = files.first()
+                .and_then(|file| Path::new(&file.path).file_name())
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            if match_size {
+                let mut by_size: HashMap<u64, Vec<Candidate>> = HashMap::new();
+                for file in files { by_size.entry(file.size).or_default().push(file); }
+                groups.extend(by_size.into_values().map(|files| CandidateGroup { name: name.clone(), files, first_row: 0 }));
+            } else {
+                groups.push(CandidateGroup { name, files, first_row: 0 });
+            }
+        }
+        groups.retain(|group| group.files.len() > 1);
+
+        // the groups that waste the most space first
+        groups.sort_by_key(|group| std::cmp::Reverse(group.files.iter().map(|file| file.size).sum::<u64>()));
+        groups.truncate(MAX_GROUPS);
+        Some(groups)
+    }
+
+    // The state and content number of each file, duplicate when another file of the group has the same content.
+    // Files with the same hash are compared byte by byte. None if stopped
+    fn confirm_group(group: &CandidateGroup, stopped: &dyn Fn() -> bool) -> Option<Vec<(DuplicateState, i32)>> {
+        let mut keys = Vec::new();
+        for file in &group.files {
+            if stopped() { return None; }
+            let same_size = group.files.iter().filter(|other| other.size == file.size).count() > 1;
+            keys.push(if same_size { hash_file(&file.path).map(|hash| (file.size, hash)) } else { None });
+        }
+
+        let mut contents = vec![0; keys.len()];
+        let mut firsts: Vec<usize> = Vec::new(); // the first file of each content
+        for (index, key) in keys.iter().enumerate() {
+            if key.is_none() { continue; }
+            if stopped() { return None; }
+            let same = firsts.iter()
+                .find(|&&first| keys[first] == *key && same_content(&group.files[first].path, &group.files[index].path));
+            match same {
+                Some(&first) => contents[index] = contents[first],
+                None => {
+                    firsts.push(index);
+                    contents[index] = firsts.len() as i32;
+                },
+            }
+        }
+
+        Some(contents.iter().map(|&content| {
+            let copies = contents.iter().filter(|&&other| other == content).count();
+            if content != 0 && copies > 1 { (DuplicateState::Duplicate, content) } else { (DuplicateState::Unique, content) }
+        }).collect())
+    }
+}
+
+// whether two files have the same bytes, false if one can not be read
+fn same_content(path: &str, other_path: &str) -> bool {
+    let (Ok(mut file), Ok(mut other)) = (fs::File::open(path), fs::File::open(other_path)) else { return false; };
+    let (mut buf, mut other_buf) = (vec![0u8; 64 * 1024], vec![0u8; 64 * 1024]);
+    loop {
+        let (Some(read), Some(other_read)) = (read_full(&mut file, &mut buf), read_full(&mut other, &mut other_buf)) else { return false; };
+        if read != other_read || buf[..read] != other_buf[..read] { return false; }
+        if read == 0 { return true; }
+    }
+}
+
+// fill the buffer unless the file ends first
+fn read_full(file: &mut fs::File, buf: &mut [u8]) -> Option<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let read = file.read(&mut buf[filled..]).ok()?;
+        if read == 0 { break; }
+        filled += read;
+    }
+    Some(filled)
+}
+
+fn hash_file(path: &str) -> Option<u64> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf).ok()?;
+        if read == 0 { break; }
+        hasher.write(&buf[..read]);
+    }
+    Some(hasher.finish())
+}
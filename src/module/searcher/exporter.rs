@@ -0,0 +1,100 @@
+use std::error::Error;
+use std::fs;
+use arboard::Clipboard;
+use wfd::DialogParams;
+
+use crate::ui::ExportFormat;
+use super::volume::SearchResultItem;
+
+// one exported result, size and date are missing for folders and files that can not be read
+struct ExportRow {
+    name: String,
+    path: String,
+    size: Option<u64>,
+    date_modified: Option<String>,
+}
+
+impl ExportRow {
+    fn new(item: SearchResultItem) -> ExportRow {
+        let path = item.path + item.file_name.as_str();
+        let metadata = fs::metadata(&path).ok();
+        ExportRow {
+            size: metadata.as_ref().filter(|metadata| metadata.is_file()).map(|metadata| metadata.len()),
+            date_modified: metadata.as_ref()
+                .and_then(|metadata| metadata.modified().ok())
+                .map(|modified| chrono::DateTime::<chrono::Local>::from(modified).format("%Y-%m-%d %H:%M:%S").to_string()),
+            name: item.file_name,
+            path,
+        }
+    }
+}
+
+// Write the results to the clipboard or to a file picked by the user
+pub fn export(items: Vec<SearchResultItem>, format: ExportFormat, to_file: bool) -> Result<(), Box<dyn Error>> {
+    let text = match format {
+        ExportFormat::Paths => items.into_iter()
+            .map(|item| item.path + item.file_name.as_str())
+            .collect::<Vec<_>>()
+            .join("\r\n"),
+        ExportFormat::Csv => to_csv(items.into_iter().map(ExportRow::new).collect()),
+        ExportFormat::Json => to_json(items.into_iter().map(ExportRow::new).collect())?,
+    };
+
+    let (file_type, extension) = match format {
+        ExportFormat::Paths => (("Text Files", "*.txt"), "txt"),
+        ExportFormat::Csv => (("CSV Files", "*.csv"), "csv"),
+        ExportFormat::Json => (("JSON Files", "*.json"), "json"),
+    };
+    write_text(text, to_file, "Export search results", file_type, extension)
+}
+
+// Put text on the clipboard or save it to a file picked by the user, csv files get a BOM for Excel
+pub fn write_text(text: String, to_file: bool, title: &str, file_type: (&str, &str), extension: &str) -> Result<(), Box<dyn Error>> {
+    if !to_file {
+        Clipboard::new()?.set_text(text)?;
+        return Ok(());
+    }
+
+    let file_name = chrono::Local::now().format("Rotor_%Y-%m-%d-%H-%M-%S.").to_string() + extension;
+    let params = DialogParams {
+        title,
+        file_types: vec![file_type],
+        default_extension: extension,
+        file_name: &file_name,
+        ..Default::default()
+    };
+    if let Ok(dialog_result) = wfd::save_dialog(params) {
+        let text = if extension == "csv" { "\u{feff}".to_string() + &text } else { text };
+        fs::write(dialog_result.selected_file_path, text)?;
+    }
+    Ok(())
+}
+
+fn to_csv(rows: Vec<ExportRow>) -> String {
+    let mut csv = String::from("name,path,size,date_modified\r\n");
+    for row in rows {
+        csv += &format!(
+            "{},{},{},{}\r\n",
+            csv_field(&row.name),
+            csv_field(&row.path),
+            row.size.map(|size| size.to_string()).unwrap_or_default(),
+            row.date_modified.unwrap_or_default()
+        );
+    }
+    csv
+}
+
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) { format!("\"{}\"", field.replace('"', "\"\"")) }
+    else { field.to_string() }
+}
+
+fn to_json(rows: Vec<ExportRow>) -> Result<String, Box<dyn Error>> {
+    let rows = rows.into_iter().map(|row| serde_json::json!({
+        "name": row.name,
+        "path": row.path,
+        "size": row.size,
+        "date_modified": row.date_modified,
+    })).collect::<Vec<_>>();
+    Ok(serde_json::to_string_pretty(&rows)?)
+}
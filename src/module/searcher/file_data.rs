@@ -1,17 +1,29 @@
 use std::ffi::{CStr, CString};
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex, mpsc};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
+use std::time::{Duration, Instant};
 use std::collections::VecDeque;
 use windows::Win32::Storage::FileSystem;
 use windows::Win32::Foundation;
-use slint::{Model, VecModel};
+use slint::VecModel;
 
-use crate::util::{file_util, log_util};
-use crate::ui::{SearchResult_slint, SearchResultType};
-use super::{SearchWindow, SearcherMessage};
-use super::volume::{Volume, SearchResultItem};
+use crate::util::log_util;
+use crate::core::application::app_config::AppConfig;
+use crate::ui::{SearchRoot_slint, SearchRootState, IndexPhase, IndexStatus_slint, ExportFormat};
+use super::{SearchWindow, SearcherMessage, exporter};
+use super::archive::ArchiveIndex;
+use super::query_server::QueryServer;
+use super::content::{ContentIndex, CONTENT_PREFIX};
+use super::provider::{FileProvider, ResultSink};
+use super::volume::{Volume, VolumeKind, IndexProgress, SearchResultItem};
 
 
+const MAX_CONTENT_RESULTS: usize = 200;
+
 #[derive(Debug)]
 enum FileState {
     Unbuild,
@@ -20,8 +32,19 @@ enum FileState {
 }
 
 struct VolumePack {
+    kind: VolumeKind,
     volume: Arc<Mutex<Volume>>,
     stop_sender: mpsc::Sender<()>,
+    building: Arc<AtomicBool>, // true while the volume is indexed in the background
+    progress: Arc<Mutex<IndexProgress>>,
+}
+
+// A volume as seen from outside the searcher thread, e.g. by the query server
+#[derive(Clone)]
+pub struct SharedVolume {
+    pub volume: Arc<Mutex<Volume>>,
+    pub building: Arc<AtomicBool>,
+    pub progress: Arc<Mutex<IndexProgress>>, // readable while the volume is locked by a build
 }
 
 pub struct SearchResult {
@@ -31,25 +54,43 @@ pub struct SearchResult {
 
 pub struct FileData {
     vols: Vec<char>,
+    roots: Vec<String>,
     finding_name: String,
     finding_result: SearchResult,
+    archive_shown: usize, // entries of archives taken for the current query, a page more comes with each page of files
+    content_stop: Option<mpsc::Sender<()>>, // stops the running content search, None once its matches arrived
     waiting_finder: u8,
     search_win: slint::Weak<SearchWindow>,
+    sink: ResultSink, // the rows of the file provider
+    msg_sender: mpsc::Sender<SearcherMessage>,
     volume_packs: Vec<VolumePack>,
+    progress_list: Arc<Mutex<Vec<Arc<Mutex<IndexProgress>>>>>, // shared with the progress reporter
+    shared_volumes: Arc<Mutex<Vec<SharedVolume>>>,
+    pending_query: Option<String>,
+    idle_since: Option<Instant>, // when the search window was hidden with the idle residency policy
     state: FileState,
     show_num: usize,
     batch: u8,
 }
 
 impl FileData {
-    pub fn new(search_win: slint::Weak<SearchWindow>) -> FileData {
+    pub fn new(search_win: slint::Weak<SearchWindow>, sink: ResultSink, msg_sender: mpsc::Sender<SearcherMessage>) -> FileData {
         FileData {
             vols: Vec::new(),
+            roots: Vec::new(),
             volume_packs: Vec::new(),
+            progress_list: Arc::new(Mutex::new(Vec::new())),
+            shared_volumes: Arc::new(Mutex::new(Vec::new())),
+            pending_query: None,
+            idle_since: None,
             finding_name: String::new(),
             finding_result: SearchResult{items: Vec::new(), query: String::new()},
+            archive_shown: 0,
+            content_stop: None,
             waiting_finder: 0,
             search_win,
+            sink,
+            msg_sender,
             state: FileState::Unbuild,
             show_num: 20,
             batch: 20,
@@ -60,23 +101,42 @@ impl FileData {
         msg_reciever: mpsc::Receiver<SearcherMessage>,
         mut file_data: FileData
     ) {
+        Self::watch_drives(file_data.msg_sender.clone());
+        Self::report_progress(file_data.progress_list.clone(), file_data.search_win.clone());
+
         std::thread::spawn(move || {
             let mut wait_deals: VecDeque<SearcherMessage> = VecDeque::new();
             loop {
-                let msg: Result<SearcherMessage, mpsc::RecvError> = if !wait_deals.is_empty() {
-                    wait_deals.pop_front().ok_or(mpsc::RecvError)
+                let msg: Result<SearcherMessage, mpsc::RecvTimeoutError> = if !wait_deals.is_empty() {
+                    wait_deals.pop_front().ok_or(mpsc::RecvTimeoutError::Disconnected)
                 } else {
-                    msg_reciever.recv()
+                    msg_reciever.recv_timeout(Duration::from_secs(30)) // wake up to check the idle timeout
                 };
 
                 match msg {
                     Ok(SearcherMessage::Init) => {
                         file_data.init_volumes();
-                        file_data.state = FileState::Released;
+                        if !file_data.is_building() { file_data.state = FileState::Released; }
+                    },
+                    Ok(SearcherMessage::Built) => {
+                        file_data.publish_searchable();
+                        file_data.publish_root_status(false);
+                        if let FileState::Unbuild = file_data.state {
+                            if !file_data.is_building() {
+                                file_data.state = FileState::Released;
+                                if let Some(query) = file_data.pending_query.take() {
+                                    wait_deals.push_back(SearcherMessage::Find(query));
+                                }
+                            }
+                        }
                     },
                     Ok(SearcherMessage::Update) => {
-                        file_data.update_index();
-                        file_data.state = FileState::Ready;
+                        if let FileState::Unbuild = file_data.state {} // nothing to update before the first build
+                        else {
+                            file_data.idle_since = None;
+                            file_data.update_index();
+                            file_data.state = FileState::Ready;
+                        }
                     },
                     Ok(SearcherMessage::Find(filename)) => {
                         match file_data.state {
@@ -90,11 +150,41 @@ impl FileData {
                                     wait_deals.push_back(rtn);
                                 }
                             },
-                            _ => {},
+                            FileState::Unbuild => {
+                                file_data.queue_query(filename);
+                            },
                         }
                     },
+                    Ok(SearcherMessage::Export(format, to_file)) => {
+                        file_data.export(format, to_file);
+                    },
                     Ok(SearcherMessage::Release) => {
                         if let FileState::Ready = file_data.state { 
+                            let residency = AppConfig::global()
+                                .lock()
+                                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                                .get_index_residency();
+                            match residency {
+                                1 => { // release after idle minutes
+                                    file_data.idle_since = Some(Instant::now());
+                                    file_data.enforce_memory_budget();
+                                },
+                                2 => { file_data.enforce_memory_budget(); }, // always resident
+                                _ => {
+                                    file_data.release_index();
+                                    file_data.state = FileState::Released;
+                                },
+                            }
+                        }
+                    },
+                    Ok(SearcherMessage::DrivesChanged) => {
+                        file_data.sync_volumes();
+                    },
+                    Ok(SearcherMessage::ContentFound(query, items)) => {
+                        file_data.content_found(query, items);
+                    },
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        if file_data.idle_expired() {
                             file_data.release_index();
                             file_data.state = FileState::Released;
                         }
@@ -105,6 +195,65 @@ impl FileData {
         });
     }
 
+    // Poll the logical drives and report when a drive was attached or removed
+    fn watch_drives(msg_sender: mpsc::Sender<SearcherMessage>) {
+        std::thread::spawn(move || {
+            let mut last_mask = unsafe { FileSystem::GetLogicalDrives() };
+            loop {
+                thread::sleep(Duration::from_secs(2));
+                let bit_mask = unsafe { FileSystem::GetLogicalDrives() };
+                if bit_mask == last_mask { continue; }
+                last_mask = bit_mask;
+                if msg_sender.send(SearcherMessage::DrivesChanged).is_err() { break; }
+            }
+        });
+    }
+
+    // Publish the progress of busy volumes to the status line of the search window
+    fn report_progress(
+        progress_list: Arc<Mutex<Vec<Arc<Mutex<IndexProgress>>>>>,
+        search_win: slint::Weak<SearchWindow>
+    ) {
+        std::thread::spawn(move || {
+            let mut was_busy = false;
+            loop {
+                thread::sleep(Duration::from_millis(300));
+                let status_list = progress_list
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .iter()
+                    .filter_map(|progress| {
+                        let progress = progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                        if progress.phase == IndexPhase::Idle { return None; }
+                        Some(IndexStatus_slint {
+                            name: progress.name.clone().into(),
+                            phase: progress.phase,
+                            records: progress.records as i32,
+                            elapsed: progress.started.elapsed().as_secs() as i32,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                // publish once more after the last busy volume became idle to clear the line
+                if status_list.is_empty() && !was_busy { continue; }
+                was_busy = !status_list.is_empty();
+
+                search_win.upgrade_in_event_loop(move |search_win| {
+                    search_win.set_index_status(Rc::new(VecModel::from(status_list)).into());
+                }).unwrap_or_else(|e| log_util::log_error(format!("report_progress: {}", e)));
+            }
+        });
+    }
+
+    // Keep the latest query typed before the first build finished, it is answered once the volumes are ready
+    fn queue_query(&mut self, query: String) {
+        let query_pending = !query.is_empty();
+        self.pending_query = if query_pending { Some(query) } else { None };
+        self.search_win.upgrade_in_event_loop(move |search_win| {
+            search_win.set_query_pending(query_pending);
+        }).unwrap_or_else(|e| log_util::log_error(format!("queue_query: {}", e)));
+    }
+
     // Check whether the disk represented by a drive letter is in ntfs format
     fn is_ntfs(vol: char) -> bool {
         if let Ok(root_path_name) = CString::new(format!("{}:\\", vol)) {
@@ -141,44 +290,186 @@ impl FileData {
             vol = (vol as u8 + 1) as char;
             bit_mask >>= 1;
         }
+        self.update_valid_roots();
 
+        let mut removed_kinds = Vec::new();
         self.volume_packs.retain(|volume_pack| {
-            if let Ok(volume)  = volume_pack.volume.lock() {
-                return self.vols.contains(&volume.drive);
-            }
-            false
+            let valid = match &volume_pack.kind {
+                VolumeKind::Ntfs(drive) => self.vols.contains(drive),
+                VolumeKind::Folder(root) => self.roots.contains(root),
+            };
+            if !valid { removed_kinds.push(volume_pack.kind.clone()); }
+            valid
         });
 
+        for kind in removed_kinds {
+            let _ = fs::remove_file(Volume::fd_path(&kind)); // the index may never have been written
+        }
+        self.refresh_shared_lists();
+
         self.vols.len() as u8
     }
 
-    fn update_result_model(&mut self, filename: String, update_result: Vec<SearchResultItem>, increment_find: bool) {
-        self.search_win.clone().upgrade_in_event_loop(move |search_win| {
-            if search_win.get_query() != filename {return;}
+    // Keep the configured roots that are reachable and not already covered by a ntfs volume
+    fn update_valid_roots(&mut self) {
+        let config_roots = AppConfig::global()
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get_search_roots();
 
-            let mut result_list = Vec::new();
-            for (id, item) in update_result.into_iter().enumerate() {
-                let icon = 
-                    file_util::get_icon((item.path.clone() + item.file_name.as_str()).as_str())
-                        .unwrap_or_default();
-                result_list.push(
-                    SearchResult_slint { 
-                        id: id as i32,
-                        icon,
-                        filename: slint::SharedString::from(item.file_name.clone()),
-                        path: slint::SharedString::from(item.path.clone()),
-                        result_type: SearchResultType::File,
-                    }
-                );
-            }
+        self.roots = config_roots.into_iter()
+            .filter(|root| !self.is_covered(root) && Path::new(root).exists())
+            .collect();
+    }
 
-            if let Some(search_result_model) = search_win.get_search_result().as_any().downcast_ref::<VecModel<SearchResult_slint>>() {
-                search_result_model.set_vec(result_list);
-                if !increment_find {
-                    search_win.set_viewport_y(0.);
-                    search_win.set_active_id(0);
-                }
+    // Whether a volume would still get a pack, a plugged in ntfs drive or a configured root that is reachable
+    fn is_available(kind: &VolumeKind) -> bool {
+        match kind {
+            VolumeKind::Ntfs(drive) => {
+                let bit_mask = unsafe { FileSystem::GetLogicalDrives() };
+                bit_mask & (1 << (*drive as u8 - b'A')) != 0 && Self::is_ntfs(*drive)
+            },
+            VolumeKind::Folder(root) => Path::new(root).exists() && AppConfig::global()
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .get_search_roots()
+                .contains(root),
+        }
+    }
+
+    // Check whether a root lies on a drive which is indexed through the MFT already
+    fn is_covered(&self, root: &str) -> bool {
+        let mut chars = root.chars();
+        match (chars.next(), chars.next()) {
+            (Some(drive), Some(':')) => self.vols.contains(&drive.to_ascii_uppercase()),
+            _ => false,
+        }
+    }
+
+    fn add_volume_pack(&mut self, kind: VolumeKind) -> &VolumePack {
+        let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+        let volume = Volume::new(kind.clone(), stop_receiver);
+        let progress = volume.progress();
+        let volume = Arc::new(Mutex::new(volume));
+        let building = Arc::new(AtomicBool::new(false));
+        self.volume_packs.push(VolumePack { kind, volume, stop_sender, building, progress });
+        self.refresh_shared_lists();
+        &self.volume_packs[self.volume_packs.len() - 1]
+    }
+
+    fn refresh_shared_lists(&self) {
+        *self.progress_list.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) =
+            self.volume_packs.iter().map(|pack| pack.progress.clone()).collect();
+        *self.shared_volumes.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) =
+            self.volume_packs.iter().map(|pack| SharedVolume {
+                volume: pack.volume.clone(),
+                building: pack.building.clone(),
+                progress: pack.progress.clone(),
+            }).collect();
+    }
+
+    // the live volumes, kept up to date when drives and roots come and go
+    pub fn shared_volumes(&self) -> Arc<Mutex<Vec<SharedVolume>>> {
+        self.shared_volumes.clone()
+    }
+
+    // the volume packs that can be searched right now
+    fn ready_packs(&self) -> impl Iterator<Item = &VolumePack> {
+        self.volume_packs.iter().filter(|pack| !pack.building.load(Ordering::Acquire))
+    }
+
+    fn is_building(&self) -> bool {
+        self.volume_packs.iter().any(|pack| pack.building.load(Ordering::Acquire))
+    }
+
+    // Add a volume and build its index on another thread, a Built message is sent when it is done
+    fn build_in_background(&mut self, kind: VolumeKind) {
+        let pack = self.add_volume_pack(kind);
+        let (volume, building) = (pack.volume.clone(), pack.building.clone());
+        building.store(true, Ordering::Release);
+
+        let msg_sender = self.msg_sender.clone();
+        thread::spawn(move || {
+            let mut volume = volume.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            volume.build_index();
+            // the drive or root may have gone while it was indexed, its pack is gone then
+            if !Self::is_available(&volume.kind) { let _ = fs::remove_file(Volume::fd_path(&volume.kind)); }
+            drop(volume);
+            building.store(false, Ordering::Release);
+            let _ = msg_sender.send(SearcherMessage::Built);
+        });
+    }
+
+    // Index volumes that appeared since the last check and drop the ones that are gone
+    pub fn sync_volumes(&mut self) {
+        self.update_valid_vols();
+
+        let new_kinds = self.vols.iter().map(|&c| VolumeKind::Ntfs(c))
+            .chain(self.roots.iter().map(|root| VolumeKind::Folder(root.clone())))
+            .filter(|kind| !self.volume_packs.iter().any(|pack| &pack.kind == kind))
+            .collect::<Vec<_>>();
+
+        for kind in new_kinds { self.build_in_background(kind); }
+
+        self.publish_searchable();
+        self.publish_root_status(true);
+    }
+
+    // Tell the search window which drives and roots are searchable
+    fn publish_searchable(&self) {
+        let searchable = self.ready_packs().map(|pack| {
+            match &pack.kind {
+                VolumeKind::Ntfs(drive) => format!("{}:", drive),
+                VolumeKind::Folder(root) => root.clone(),
             }
+        }).collect::<Vec<_>>().join("  ");
+
+        self.search_win.upgrade_in_event_loop(move |search_win| {
+            search_win.set_searchable(searchable.into());
+        }).unwrap_or_else(|e| log_util::log_error(format!("publish_searchable: {}", e)));
+    }
+
+    // Send the state of every configured root to the setting window
+    fn publish_root_status(&self, updating: bool) {
+        let (setting_win, config_roots) = {
+            let app_config = AppConfig::global()
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            (app_config.setting_win.clone(), app_config.get_search_roots())
+        };
+
+        if let Some(setting_win) = setting_win {
+            let root_list = config_roots.into_iter().map(|root| {
+                let kind = VolumeKind::Folder(root.clone());
+                let (state, entries) = match self.volume_packs.iter().find(|pack| pack.kind == kind) {
+                    Some(VolumePack{building, ..}) if building.load(Ordering::Acquire) => (SearchRootState::Indexing, 0),
+                    Some(VolumePack{volume, ..}) => {
+                        let entries = volume.lock()
+                            .unwrap_or_else(|poisoned| poisoned.into_inner())
+                            .entry_count();
+                        if entries > 0 { (SearchRootState::Ready, entries) }
+                        else if updating { (SearchRootState::Indexing, 0) }
+                        else { (SearchRootState::Offline, 0) }
+                    },
+                    None if self.is_covered(&root) => (SearchRootState::Covered, 0),
+                    None => (SearchRootState::Offline, 0),
+                };
+                SearchRoot_slint { path: root.into(), state, entries: entries as i32 }
+            }).collect::<Vec<_>>();
+
+            setting_win.upgrade_in_event_loop(move |setting_win| {
+                setting_win.set_search_roots(Rc::new(VecModel::from(root_list)).into());
+            }).unwrap_or_else(|e| log_util::log_error(format!("publish_root_status: {}", e)));
+        }
+    }
+
+    fn update_result_model(&mut self, filename: String, update_result: Vec<SearchResultItem>, increment_find: bool) {
+        let items = update_result.into_iter().map(FileProvider::to_result_item).collect();
+        self.sink.publish(&filename, items, !increment_find);
+
+        self.search_win.upgrade_in_event_loop(move |search_win| {
+            if search_win.get_query() != filename {return;}
+            search_win.set_query_pending(false);
         }).unwrap_or_else(|e| log_util::log_error(format!("update_result_model: {}", e)));
     }
 
@@ -189,6 +480,9 @@ impl FileData {
         if self.finding_name == filename { 
             increment_find = true;
             self.show_num += self.batch as usize;
+            if Self::content_phrase(&filename).is_none() && !filename.is_empty() && Self::search_archives() {
+                self.add_archive_page(&filename);
+            }
 
             if self.finding_result.items.len() > self.show_num {
                 let return_result = self.finding_result.items[..self.show_num].to_vec();
@@ -200,13 +494,42 @@ impl FileData {
             self.show_num = self.batch as usize;
             self.finding_result.items.clear();
             self.finding_result.query = filename.clone();
+            self.stop_content_search();
+
+            if let Some(phrase) = Self::content_phrase(&filename) {
+                // all matches at once, the files are read on a worker thread which reports back with ContentFound
+                let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+                self.content_stop = Some(stop_sender);
+                let (phrase, query, msg_sender) = (phrase.to_string(), filename.clone(), self.msg_sender.clone());
+                thread::spawn(move || {
+                    if let Some(items) = ContentIndex::search(&phrase, MAX_CONTENT_RESULTS, &stop_receiver) {
+                        let _ = msg_sender.send(SearcherMessage::ContentFound(query, items));
+                    }
+                });
+                return reply;
+            } else if !filename.is_empty() && Self::search_archives() {
+                self.archive_shown = 0;
+                self.add_archive_page(&filename);
+            }
         }
         
         if filename.is_empty() { return reply; } 
 
-        self.waiting_finder = self.volume_packs.len() as u8;
+        if Self::content_phrase(&filename).is_some() {
+            if self.content_stop.is_some() { return reply; } // the matches are shown when they arrive
+            let return_result = self.finding_result.items.iter().take(self.show_num).cloned().collect();
+            self.update_result_model(filename, return_result, increment_find);
+            return reply;
+        }
+
+        self.waiting_finder = self.ready_packs().count() as u8;
+        if self.waiting_finder == 0 {
+            self.update_result_model(filename, Vec::new(), increment_find);
+            return reply;
+        }
+
         let (find_result_sender, find_result_receiver) = mpsc::channel::<Option<Vec<SearchResultItem>>>();
-        for VolumePack{volume, ..} in &mut self.volume_packs {
+        for VolumePack{volume, ..} in self.ready_packs() {
             let find_result_sender: mpsc::Sender<Option<Vec<SearchResultItem>>> = find_result_sender.clone();
             let batch = self.batch;
             let volume = volume.clone();
@@ -246,35 +569,54 @@ impl FileData {
         reply
     }
 
+    // Show the matches of a content query unless another query was started meanwhile
+    pub fn content_found(&mut self, query: String, items: Vec<SearchResultItem>) {
+        if self.finding_name != query { return; }
+        self.content_stop = None;
+        self.finding_result.items = items;
+        let return_result = self.finding_result.items.iter().take(self.show_num).cloned().collect();
+        self.update_result_model(query, return_result, false);
+    }
+
+    fn stop_content_search(&mut self) {
+        if let Some(stop_sender) = self.content_stop.take() { let _ = stop_sender.send(()); }
+    }
+
+    // Add the next page of matching entries of archives, in rank order among the files found so far
+    fn add_archive_page(&mut self, filename: &str) {
+        let mut items = ArchiveIndex::global()
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .search(filename, self.archive_shown, self.batch as usize);
+        self.archive_shown += items.len();
+        self.finding_result.items.append(&mut items);
+        self.finding_result.items.sort_by(|a, b| b.rank.cmp(&a.rank));
+    }
+
     pub fn init_volumes(&mut self) {
         self.volume_packs.clear();
         self.update_valid_vols();
 
-        let handles = self.vols.iter().map(|&c| {
-            let (stop_sender, stop_receiver) = mpsc::channel::<()>();
-            let volume = Arc::new(Mutex::new(Volume::new(c, stop_receiver)));
-            
-            self.volume_packs.push(VolumePack { volume: volume.clone(), stop_sender });
-
-            thread::spawn(move || {
-                volume
-                    .lock()
-                    .unwrap_or_else(|poisoned| poisoned.into_inner())
-                    .build_index();
-            })
-        }).collect::<Vec<_>>();
+        let kinds = self.vols.iter().map(|&c| VolumeKind::Ntfs(c))
+            .chain(self.roots.iter().map(|root| VolumeKind::Folder(root.clone())))
+            .collect::<Vec<_>>();
+        for kind in kinds { self.build_in_background(kind); }
 
-        for handle in handles {
-            if let Err(e) = handle.join() {
-                eprintln!("Thread panicked: {:?}", e); // TODO handle error
-            }
-        }
+        self.publish_searchable();
+        self.publish_root_status(true);
     }
 
     pub fn update_index(&mut self) {
         self.update_valid_vols();
 
-        let handles = self.volume_packs.iter().map(|VolumePack{volume, ..}| {
+        // roots added in the setting window since the last update
+        for root in self.roots.clone() {
+            let kind = VolumeKind::Folder(root);
+            if !self.volume_packs.iter().any(|pack| pack.kind == kind) { self.add_volume_pack(kind); }
+        }
+        self.publish_root_status(true);
+
+        let handles = self.ready_packs().map(|VolumePack{volume, ..}| {
             let volume = volume.clone();
             thread::spawn(move || {
                 volume
@@ -289,13 +631,116 @@ impl FileData {
                 eprintln!("Thread panicked: {:?}", e); // TODO handle error
             }
         }
+        self.publish_searchable();
+        self.publish_root_status(false);
+
+        let (search_archives, content_search) = {
+            let app_config = AppConfig::global()
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            (app_config.get_search_archives(), app_config.get_content_search())
+        };
+        let volumes = self.shared_volumes.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        if search_archives { ArchiveIndex::refresh(volumes.clone()); }
+        if content_search { ContentIndex::refresh(volumes); }
+    }
+
+    fn search_archives() -> bool {
+        AppConfig::global()
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get_search_archives()
+    }
+
+    // the phrase of a "content:" query, None for a file name query or with content search turned off
+    fn content_phrase(query: &str) -> Option<&str> {
+        let phrase = query.strip_prefix(CONTENT_PREFIX)?;
+        let content_search = AppConfig::global()
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get_content_search();
+        if content_search { Some(phrase) } else { None }
+    }
+
+    // Whether the index has been kept long enough after the search window was hidden
+    fn idle_expired(&self) -> bool {
+        if let Some(idle_since) = self.idle_since {
+            let app_config = AppConfig::global()
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            return match app_config.get_index_residency() {
+                0 => true, // the policy was changed to release on blur meanwhile
+                1 => idle_since.elapsed().as_secs() >= app_config.get_index_idle_minutes() as u64 * 60,
+                _ => false,
+            };
+        }
+        false
+    }
+
+    // Release the volumes that answered the fewest queries until the loaded ones fit in the memory budget
+    fn enforce_memory_budget(&mut self) {
+        let budget = AppConfig::global()
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get_index_memory_budget() as usize * 1024 * 1024;
+        if budget == 0 { return; }
+
+        let mut loaded = self.ready_packs().filter_map(|VolumePack{volume, ..}| {
+            let guard = volume.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if guard.is_loaded() { Some((volume.clone(), guard.hits(), guard.memory_size())) }
+            else { None }
+        }).collect::<Vec<_>>();
+        loaded.sort_by_key(|(_, hits, _)| *hits);
+
+        let mut total = loaded.iter().map(|(_, _, size)| size).sum::<usize>();
+        for (volume, _, size) in loaded {
+            if total <= budget { break; }
+            volume
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .release_index();
+            total -= size;
+        }
+        self.finding_name = String::new();
+    }
+
+    // Export every match of the current query, not only the shown rows, with the entries of archives or
+    // the matching text files like the result list
+    pub fn export(&mut self, format: ExportFormat, to_file: bool) {
+        if self.finding_name.is_empty() { return; }
+
+        let content_items = Self::content_phrase(&self.finding_name).map(|_| self.finding_result.items.clone());
+        let search_archives = content_items.is_none() && Self::search_archives();
+        let (volumes, query) = (self.shared_volumes.clone(), self.finding_name.clone());
+        // searching every volume and reading sizes and dates of many files takes a while
+        thread::spawn(move || {
+            let mut items = match content_items {
+                Some(items) => items, // all of them were found at once
+                None => {
+                    let (_stop_sender, stop_receiver) = mpsc::channel::<()>(); // never stopped
+                    let mut items = QueryServer::search_volumes(&volumes, &query, 0, &stop_receiver).unwrap_or_default();
+                    if search_archives {
+                        items.append(&mut ArchiveIndex::global()
+                            .lock()
+                            .unwrap_or_else(|poisoned| poisoned.into_inner())
+                            .search(&query, 0, usize::MAX));
+                    }
+                    items
+                },
+            };
+            items.sort_by_key(|item| std::cmp::Reverse(item.rank)); // same order as the result list
+            exporter::export(items, format, to_file)
+                .unwrap_or_else(|e| log_util::log_error(format!("Failed to export search result: {:?}", e)));
+        });
     }
 
     pub fn release_index(&mut self) {
         self.update_valid_vols();
         
+        self.idle_since = None;
         self.finding_name = String::new();
-        let handles = self.volume_packs.iter().map(|VolumePack{volume, ..}| {
+        self.stop_content_search();
+        let handles = self.ready_packs().map(|VolumePack{volume, ..}| {
             let volume = volume.clone();
             thread::spawn(move || {
                 volume
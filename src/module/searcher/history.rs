@@ -0,0 +1,91 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use serde::{Serialize, Deserialize};
+
+use crate::util::file_util;
+
+const MAX_QUERIES: usize = 100;
+
+// The queries a result was opened from, newest first
+#[derive(Default, Serialize, Deserialize)]
+pub struct SearchHistory {
+    queries: Vec<String>,
+}
+
+impl SearchHistory {
+    fn new() -> SearchHistory {
+        fs::read_to_string(Self::cache_path())
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn global() -> &'static Mutex<SearchHistory> {
+        &INSTANCE
+    }
+
+    fn cache_path() -> PathBuf {
+        file_util::get_userdata_path().join("search_history.json")
+    }
+
+    fn save(&self) -> Result<(), Box<dyn Error>> {
+        let root_path = file_util::get_userdata_path();
+        if !root_path.exists() { fs::create_dir_all(&root_path)?; }
+        fs::write(Self::cache_path(), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, query: &str) -> Result<(), Box<dyn Error>> {
+        let query = query.trim();
+        if query.is_empty() { return Ok(()); }
+        self.queries.retain(|q| q != query);
+        self.queries.insert(0, query.to_string());
+        self.queries.truncate(MAX_QUERIES);
+        self.save()
+    }
+
+    pub fn clear(&mut self) -> Result<(), Box<dyn Error>> {
+        self.queries.clear();
+        self.save()
+    }
+
+    pub fn recent(&self, max: usize) -> Vec<String> {
+        self.queries.iter().take(max).cloned().collect()
+    }
+
+    // What the query may be completed to: the newest query of the history starting with it,
+    // else the longest prefix the file names found for it share
+    pub fn completion(&self, query: &str, file_names: &[String]) -> Option<String> {
+        if query.is_empty() { return None; }
+        if let Some(completion) = self.queries.iter().find_map(|q| extend(query, q)) { return Some(completion); }
+        complete_name(query, file_names)
+    }
+}
+
+// The query completed to the longest prefix the names starting with it share
+pub fn complete_name(query: &str, names: &[String]) -> Option<String> {
+    let query_lower = query.to_lowercase();
+    let mut names = names.iter().filter(|name| name.to_lowercase().starts_with(&query_lower));
+    let mut prefix = names.next()?.as_str();
+    for name in names {
+        let common = prefix.char_indices()
+            .zip(name.chars())
+            .find(|((_, a), b)| !a.to_lowercase().eq(b.to_lowercase()))
+            .map(|((index, _), _)| index)
+            .unwrap_or_else(|| prefix.char_indices().nth(name.chars().count()).map_or(prefix.len(), |(index, _)| index));
+        prefix = &prefix[..common];
+    }
+    extend(query, prefix)
+}
+
+// The query with the rest of a longer text starting with it, what was typed is kept as typed
+fn extend(query: &str, text: &str) -> Option<String> {
+    if text.len() <= query.len() || !text.to_lowercase().starts_with(&query.to_lowercase()) { return None; }
+    text.get(query.len()..).map(|rest| query.to_string() + rest)
+}
+
+static INSTANCE: LazyLock<Mutex<SearchHistory>> = LazyLock::new(|| {
+    Mutex::new(SearchHistory::new())
+});
@@ -0,0 +1,247 @@
+use std::error::Error;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::util::log_util;
+use super::file_data::SharedVolume;
+use super::query_server::QueryServer;
+use super::volume::SearchResultItem;
+
+// Answers search requests in the format of the HTTP server of voidtools Everything, e.g.
+//   GET /?search=foo&json=1&path_column=1&size_column=1&date_modified_column=1&offset=0&count=50&sort=name&ascending=1
+// case=1 is supported, other options of Everything (regex, wholeword, ...) are ignored.
+// Only requests to 127.0.0.1 or localhost are answered, which keeps web pages out through DNS rebinding.
+// A page holds at most MAX_COUNT rows, sorting by size or date looks at the best MAX_STAT_SORTED matches by rank.
+// A volume gives at most its best MAX_MATCHES matches by rank, the total and the other sorts count only those.
+
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000; // 1970-01-01 in 100ns ticks since 1601-01-01
+const MAX_COUNT: usize = 1000;
+const MAX_STAT_SORTED: usize = 10_000; // every one of them is read from the file system
+const MAX_MATCHES: usize = 50_000; // per volume, a query like "a" would hold most of the index otherwise
+const READ_TIMEOUT: Duration = Duration::from_secs(5); // a client that sends no request does not keep its thread
+
+struct EverythingQuery {
+    search: String,
+    json: bool,
+    path_column: bool,
+    size_column: bool,
+    date_modified_column: bool,
+    offset: usize,
+    count: usize,
+    sort: String,
+    ascending: bool,
+    case: bool,
+}
+
+impl EverythingQuery {
+    fn parse(query_string: &str) -> EverythingQuery {
+        let mut query = EverythingQuery {
+            search: String::new(),
+            json: false,
+            path_column: false,
+            size_column: false,
+            date_modified_column: false,
+            offset: 0,
+            count: MAX_COUNT,
+            sort: String::new(),
+            ascending: true,
+            case: false,
+        };
+        for pair in query_string.split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = url_decode(value);
+            let flag = value != "0" && !value.is_empty();
+            match key {
+                "search" | "s" | "q" => query.search = value,
+                "json" | "j" => query.json = flag,
+                "path_column" => query.path_column = flag,
+                "size_column" => query.size_column = flag,
+                "date_modified_column" => query.date_modified_column = flag,
+                "offset" | "o" => query.offset = value.parse().unwrap_or(0),
+                "count" | "c" => query.count = value.parse().unwrap_or(MAX_COUNT).min(MAX_COUNT),
+                "sort" => query.sort = value,
+                "ascending" => query.ascending = flag,
+                "case" | "i" => query.case = flag,
+                _ => {},
+            }
+        }
+        query
+    }
+}
+
+// one result row with the columns of Everything
+struct Row {
+    name: String,
+    path: String, // parent folder without trailing separator
+    is_folder: bool,
+    size: u64,
+    date_modified: u64, // FILETIME
+}
+
+impl Row {
+    fn new(item: &SearchResultItem) -> Row {
+        let full_path = item.path.clone() + item.file_name.as_str();
+        let metadata = fs::metadata(&full_path).ok();
+        let date_modified = metadata.as_ref()
+            .and_then(|metadata| metadata.modified().ok())
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| FILETIME_UNIX_EPOCH + duration.as_nanos() as u64 / 100)
+            .unwrap_or(0);
+        Row {
+            name: item.file_name.clone(),
+            path: item.path.trim_end_matches('\\').to_string(),
+            is_folder: metadata.as_ref().is_some_and(|metadata| metadata.is_dir()),
+            size: metadata.as_ref().map(|metadata| metadata.len()).unwrap_or(0),
+            date_modified,
+        }
+    }
+}
+
+pub struct HttpServer {}
+
+impl HttpServer {
+    pub fn start(port: u16, volumes: Arc<Mutex<Vec<SharedVolume>>>) -> Result<(), Box<dyn Error>> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let volumes = volumes.clone();
+                        thread::spawn(move || {
+                            Self::serve(stream, port, volumes)
+                                .unwrap_or_else(|e| log_util::log_error(format!("HttpServer connection: {:?}", e)));
+                        });
+                    },
+                    Err(e) => log_util::log_error(format!("HttpServer accept: {:?}", e)),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn serve(stream: TcpStream, port: u16, volumes: Arc<Mutex<Vec<SharedVolume>>>) -> Result<(), Box<dyn Error>> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut host = String::new();
+        loop { // only the host is looked at
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 || header.trim().is_empty() { break; }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("host") { host = value.trim().to_lowercase(); }
+            }
+        }
+        if host != format!("127.0.0.1:{}", port) && host != format!("localhost:{}", port) {
+            return Self::respond(stream, "403 Forbidden", "text/plain", "Forbidden");
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+        let (path, query_string) = target.split_once('?').unwrap_or((target, ""));
+
+        if method != "GET" { return Self::respond(stream, "405 Method Not Allowed", "text/plain", "Method Not Allowed"); }
+        if path != "/" { return Self::respond(stream, "404 Not Found", "text/plain", "Not Found"); }
+
+        let query = EverythingQuery::parse(query_string);
+        let (total, rows) = Self::search(&query, &volumes);
+        if query.json {
+            Self::respond(stream, "200 OK", "application/json; charset=utf-8", &Self::to_json(&query, total, &rows))
+        } else {
+            Self::respond(stream, "200 OK", "text/html; charset=utf-8", &Self::to_html(&query, total, &rows))
+        }
+    }
+
+    // the matches are collected for the total count of Everything, the columns are read for the page only
+    fn search(query: &EverythingQuery, volumes: &Mutex<Vec<SharedVolume>>) -> (usize, Vec<Row>) {
+        if query.search.is_empty() { return (0, Vec::new()); }
+
+        let (_stop_sender, stop_receiver) = mpsc::channel::<()>(); // never stopped
+        let mut items = QueryServer::search_volumes(volumes, &query.search, MAX_MATCHES, &stop_receiver).unwrap_or_default();
+        if query.case && !query.search.contains('*') {
+            items.retain(|item| item.file_name.contains(query.search.as_str()));
+        }
+        let total = items.len();
+
+        match query.sort.as_str() {
+            "name" => items.sort_by_key(|item| item.file_name.to_lowercase()),
+            "path" => items.sort_by_key(|item| (item.path.to_lowercase(), item.file_name.to_lowercase())),
+            "size" | "date_modified" => {
+                // the sort keys come from the file system, so only the best matches are read
+                items.sort_by_key(|item| std::cmp::Reverse(item.rank));
+                let mut rows = items.iter().take(MAX_STAT_SORTED).map(Row::new).collect::<Vec<_>>();
+                if query.sort == "size" { rows.sort_by_key(|row| row.size); } else { rows.sort_by_key(|row| row.date_modified); }
+                if !query.ascending { rows.reverse(); }
+                return (total, rows.into_iter().skip(query.offset).take(query.count).collect());
+            },
+            _ => items.sort_by_key(|item| std::cmp::Reverse(item.rank)), // rank order of the search window
+        }
+        if !query.ascending && (query.sort == "name" || query.sort == "path") { items.reverse(); }
+
+        (total, items.iter().skip(query.offset).take(query.count).map(Row::new).collect())
+    }
+
+    fn to_json(query: &EverythingQuery, total: usize, rows: &[Row]) -> String {
+        let results = rows.iter().map(|row| {
+            let mut result = serde_json::Map::new();
+            result.insert("type".into(), (if row.is_folder { "folder" } else { "file" }).into());
+            result.insert("name".into(), row.name.clone().into());
+            if query.path_column { result.insert("path".into(), row.path.clone().into()); }
+            if query.size_column { result.insert("size".into(), row.size.to_string().into()); }
+            if query.date_modified_column { result.insert("date_modified".into(), row.date_modified.to_string().into()); }
+            serde_json::Value::Object(result)
+        }).collect::<Vec<_>>();
+        serde_json::json!({ "totalResults": total, "results": results }).to_string()
+    }
+
+    fn to_html(query: &EverythingQuery, total: usize, rows: &[Row]) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title></head><body><p>{} results</p><table>",
+            html_escape(&query.search), total
+        );
+        for row in rows {
+            html += &format!("<tr><td>{}</td><td>{}</td></tr>", html_escape(&row.name), html_escape(&row.path));
+        }
+        html + "</table></body></html>"
+    }
+
+    fn respond(mut stream: TcpStream, status: &str, content_type: &str, body: &str) -> Result<(), Box<dyn Error>> {
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, content_type, body.len(), body
+        )?;
+        stream.flush()?;
+        Ok(())
+    }
+}
+
+// decode a query string value, "+" is a space
+fn url_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match hex {
+                    Some(byte) => { decoded.push(byte); i += 2; },
+                    None => decoded.push(b'%'),
+                }
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -1,28 +1,61 @@
 mod file_data;
 mod volume;
+mod query_server;
+mod http_server;
+mod exporter;
+mod duplicate;
+mod diagnostics;
+mod snapshot;
+mod disk_usage;
+mod archive;
+mod content;
+mod clip_history;
+mod history;
+mod provider;
+mod recent_changes;
 
 use slint::{ComponentHandle, Model};
-use std::{error::Error, rc::Rc, sync::mpsc::{self, Sender}};
+use std::{cell::RefCell, error::Error, rc::Rc, sync::mpsc::{self, Sender}};
 use i_slint_backend_winit::{winit::platform::windows::WindowExtWindows, WinitWindowAccessor};
 use global_hotkey::hotkey::HotKey;
 use xcap::Monitor;
 
 use file_data::FileData;
+use volume::SearchResultItem;
+use provider::{Providers, ResultItem, ResultAction, FileProvider, AppProvider, CalcProvider, AliasProvider, ClipProvider, UnicodeProvider, HistoryProvider, PathProvider};
+pub use clip_history::ClipHistory;
+pub use history::SearchHistory;
+use duplicate::DuplicateFinder;
+use diagnostics::Diagnostics;
+use snapshot::SnapshotManager;
+use disk_usage::DiskUsage;
+use recent_changes::RecentChanges;
+use query_server::QueryServer;
+use http_server::HttpServer;
 use crate::{sys_util, util::log_util};
 use crate::core::application::app_config::AppConfig;
 use crate::util::file_util;
-use crate::ui::SearchWindow;
+use crate::ui::{SearchWindow, ExportFormat, FileAction};
 use crate::module::{Module, ModuleMessage};
 
 pub enum SearcherMessage {
     Init,
     Update,
     Find(String),
+    Export(ExportFormat, bool), // format, to a file instead of the clipboard
     Release,
+    DrivesChanged,
+    Built,
+    ContentFound(String, Vec<SearchResultItem>), // the matches of a content query, found on a worker thread
 }
 
 pub struct Searcher {
     pub search_win: SearchWindow,
+    _duplicate_finder: DuplicateFinder,
+    _diagnostics: Diagnostics,
+    _snapshot_manager: SnapshotManager,
+    _disk_usage: Rc<DiskUsage>,
+    _recent_changes: Rc<RecentChanges>,
     searcher_msg_sender: mpsc::Sender<SearcherMessage>,
 }
 
@@ -55,6 +88,7 @@ impl Module for Searcher{
                                 }
                             }
                             let _ = searcher_msg_sender_clone_clone.send(SearcherMessage::Update);
+                            if win.get_query().is_empty() { win.invoke_query_change("".into()); } // list the recent queries
                             let _ = win.show();
                             win.window().set_size(win.window().size()); // trick: fix the bug of error scale_factor
                             win.window().with_winit_window(|winit_win: &i_slint_backend_winit::winit::window::Window| {
@@ -101,16 +135,38 @@ impl Searcher {
         search_win.set_active_id(0);
 
         let (searcher_msg_sender, searcher_msg_receiver) = mpsc::channel::<SearcherMessage>();
-        let _file_data = FileData::new(search_win.as_weak());
+        let providers = Rc::new(RefCell::new(Providers::new(search_win.as_weak())));
+        let _file_data = FileData::new(search_win.as_weak(), providers.borrow().next_sink(), searcher_msg_sender.clone());
+        providers.borrow_mut().add(Box::new(FileProvider::new(searcher_msg_sender.clone())));
+        providers.borrow_mut().add(Box::new(AppProvider::new()));
+        providers.borrow_mut().add(Box::new(CalcProvider::new()));
+        providers.borrow_mut().add(Box::new(AliasProvider::new()));
+        providers.borrow_mut().add(Box::new(ClipProvider::new()));
+        providers.borrow_mut().add(Box::new(UnicodeProvider::new()));
+        providers.borrow_mut().add(Box::new(HistoryProvider::new()));
+        providers.borrow_mut().add(Box::new(PathProvider::new(_file_data.shared_volumes())));
+        ClipHistory::start();
+        if app_config.get_query_server() {
+            QueryServer::start(app_config.get_query_server_port(), _file_data.shared_volumes())
+                .unwrap_or_else(|e| log_util::log_error(format!("Failed to start query server: {:?}", e)));
+        }
+        if app_config.get_http_server() {
+            HttpServer::start(app_config.get_http_server_port(), _file_data.shared_volumes())
+                .unwrap_or_else(|e| log_util::log_error(format!("Failed to start http server: {:?}", e)));
+        }
+        drop(app_config); // the tool windows register themselves in the config too
+        let duplicate_finder = DuplicateFinder::new(_file_data.shared_volumes())?;
+        let diagnostics = Diagnostics::new(_file_data.shared_volumes())?;
+        let snapshot_manager = SnapshotManager::new(_file_data.shared_volumes())?;
+        let disk_usage = Rc::new(DiskUsage::new(_file_data.shared_volumes())?);
+        let recent_changes = Rc::new(RecentChanges::new()?);
         FileData::event_loop(searcher_msg_receiver, _file_data);
         let _ = searcher_msg_sender.send(SearcherMessage::Init);
 
         { // add query change hander
-            let searcher_msg_sender_clone = searcher_msg_sender.clone();
-            let search_result_model_clone = search_result_model.clone();
+            let providers_clone = providers.clone();
             search_win.on_query_change(move |query| {
-                if query.is_empty() { search_result_model_clone.set_vec(vec![]); }
-                let _ = searcher_msg_sender_clone.send(SearcherMessage::Find(query.to_string()));
+                providers_clone.borrow_mut().query(&query);
             });
         }
 
@@ -133,12 +189,26 @@ impl Searcher {
 
         { // add key event hander
             let search_win_clone = search_win.as_weak();
-            let searcher_msg_sender_clone = searcher_msg_sender.clone();
+            let providers_clone = providers.clone();
             let search_result_model_clone = search_result_model.clone();
             search_win.on_key_pressed(move |event| {
                 match search_win_clone.upgrade() {
                     Some(search_win_clone) => {
-                        if event.text == slint::SharedString::from(slint::platform::Key::Escape) {
+                        if !search_win_clone.get_menu_path().is_empty() {
+                            // the action menu of a row is open
+                            let menu_active = search_win_clone.get_menu_active();
+                            let entry_count = search_win_clone.invoke_menu_entry_count();
+                            if event.text == slint::SharedString::from(slint::platform::Key::Escape) {
+                                search_win_clone.invoke_close_menu();
+                            }else if event.text == slint::SharedString::from(slint::platform::Key::UpArrow) {
+                                search_win_clone.set_menu_active((menu_active + entry_count - 1) % entry_count);
+                            }else if event.text == slint::SharedString::from(slint::platform::Key::DownArrow) {
+                                search_win_clone.set_menu_active((menu_active + 1) % entry_count);
+                            }else if event.text == slint::SharedString::from(slint::platform::Key::Return) {
+                                let action = search_win_clone.invoke_menu_entry_action(menu_active);
+                                search_win_clone.invoke_file_action(search_win_clone.get_menu_path(), action);
+                            }
+                        }else if event.text == slint::SharedString::from(slint::platform::Key::Escape) {
                             // ESC
                             let _ = search_win_clone.hide();
                         }else if event.text == slint::SharedString::from(slint::platform::Key::UpArrow) {
@@ -153,25 +223,32 @@ impl Searcher {
                         }else if event.text == slint::SharedString::from(slint::platform::Key::DownArrow) {
                             // DownArrow
                             let mut active_id = search_win_clone.get_active_id();
-                            if active_id < (search_result_model_clone.row_count() - 1) as i32 { // If no more item
+                            if active_id < search_result_model_clone.row_count() as i32 - 1 { // If no more item
                                 active_id += 1;
                                 search_win_clone.set_active_id(active_id);
                                 let viewport_y = search_win_clone.get_viewport_y();
                                 if (-viewport_y / 60. + 7.) as i32 <= active_id { search_win_clone.set_viewport_y(viewport_y - 60.); }
                             }
                             // If to the bottom, try to find more
-                            if active_id == (search_result_model_clone.row_count() - 1) as i32 {
-                                let _ = searcher_msg_sender_clone.send(SearcherMessage::Find(search_win_clone.get_query().to_string()));
+                            if active_id == search_result_model_clone.row_count() as i32 - 1 {
+                                providers_clone.borrow_mut().query(&search_win_clone.get_query());
                             }
+                        }else if event.text == slint::SharedString::from(slint::platform::Key::Return) && event.modifiers.control {
+                            // Ctrl+Enter, the action menu of the active row
+                            open_menu(&search_win_clone, &providers_clone.borrow(), search_win_clone.get_active_id());
                         }else if event.text == slint::SharedString::from(slint::platform::Key::Return) {
                             // Enter
                             let active_id = search_win_clone.get_active_id();
-                            let data = search_result_model_clone.row_data(active_id as usize);
-                            if let Some(f) = data {
-                                file_util::open_file((f.path + &f.filename).to_string())
-                                    .unwrap_or_else(|e| log_util::log_error(format!("open_file error: {:?}", e)));
-                                let _ = search_win_clone.hide();
+                            let item = providers_clone.borrow_mut().activate(active_id as usize);
+                            match item {
+                                Some(ResultItem { action: ResultAction::Query(query), .. }) => set_query(&search_win_clone, query),
+                                Some(_) => { let _ = search_win_clone.hide(); },
+                                None => {},
                             }
+                        }else if event.text == slint::SharedString::from(slint::platform::Key::Tab) {
+                            // Tab, take the completion
+                            let completion = search_win_clone.get_completion();
+                            if !completion.is_empty() { set_query(&search_win_clone, completion.to_string()); }
                         }
                     },
                     None => { log_util::log_error("Failed to upgrade search_win in key event hander".to_string()); }
@@ -180,44 +257,152 @@ impl Searcher {
         }
 
         { // add item click hander
-            let search_result_model_clone = search_result_model.clone();
+            let search_win_clone = search_win.as_weak();
+            let providers_clone = providers.clone();
             search_win.on_item_click(move |event, id| {
                 if event.kind == slint::private_unstable_api::re_exports::PointerEventKind::Up {
                     if event.button == slint::platform::PointerEventButton::Left {
-                        let data = search_result_model_clone.row_data(id as usize);
-                        if let Some(f) = data {
-                            file_util::open_file((f.path + &f.filename).to_string())
-                                .unwrap_or_else(|e| log_util::log_error(format!("open_file error: {:?}", e)));
+                        let item = providers_clone.borrow_mut().activate(id as usize);
+                        if let (Some(ResultItem { action: ResultAction::Query(query), .. }), Some(search_win)) = (item, search_win_clone.upgrade()) {
+                            set_query(&search_win, query);
+                        }
+                    } else if event.button == slint::platform::PointerEventButton::Right {
+                        if let Some(search_win) = search_win_clone.upgrade() {
+                            search_win.set_active_id(id);
+                            open_menu(&search_win, &providers_clone.borrow(), id);
                         }
                     }
                 }
             });
         }
 
+        { // on file action
+            let search_win_clone = search_win.as_weak();
+            let providers_clone = providers.clone();
+            search_win.on_file_action(move |path, action| {
+                let Some(search_win) = search_win_clone.upgrade() else { return; };
+                let path = path.to_string();
+                let result = match action {
+                    FileAction::CopyPath => file_util::copy_path(&path),
+                    FileAction::CopyFile => file_util::copy_file_to_clipboard(&path),
+                    FileAction::OpenWith => file_util::open_with(&path),
+                    FileAction::Rename => { search_win.set_renaming(true); return; }, // done by rename_file
+                    FileAction::Recycle => file_util::move_to_recycle_bin(&path)
+                        .map(|_| providers_clone.borrow().file_changed(&path, None)),
+                    FileAction::Properties => file_util::show_properties(&path),
+                };
+                result.unwrap_or_else(|e| log_util::log_error(format!("Failed to run file action {:?}: {:?}", action, e)));
+                search_win.invoke_close_menu();
+            });
+        }
+
+        { // on rename file
+            let providers_clone = providers.clone();
+            search_win.on_rename_file(move |path, new_name| {
+                let path = path.to_string();
+                match file_util::rename_file(&path, &new_name) {
+                    Ok(new_path) => providers_clone.borrow().file_changed(&path, Some(&new_path)),
+                    Err(e) => log_util::log_error(format!("Failed to rename {}: {:?}", path, e)),
+                }
+            });
+        }
+
         { // on open with admin
-            let search_result_model_clone = search_result_model.clone();
+            let providers_clone = providers.clone();
             search_win.on_open_with_admin(move |id| {
-                let data = search_result_model_clone.row_data(id as usize);
-                if let Some(f) = data {
-                    file_util::open_file_admin((f.path + &f.filename).to_string());
+                // only rows of files on disk, not urls or entries of archives
+                if let Some(path) = providers_clone.borrow().file_path(id as usize) {
+                    file_util::open_file_admin(path);
                 }
             });
         }
 
         { // on open file dir
-            let search_result_model_clone = search_result_model.clone();
+            let providers_clone = providers.clone();
             search_win.on_open_file_dir(move |id| {
-                let data = search_result_model_clone.row_data(id as usize);
-                if let Some(f) = data {
-                    file_util::open_file((f.path[..(f.path.len()-1)]).to_string())
+                let path = providers_clone.borrow().file_path(id as usize);
+                if let Some(folder) = path.and_then(|path| archive::containing_folder(&path)) {
+                    file_util::open_file(folder)
                         .unwrap_or_else(|e| log_util::log_error(format!("open_file error: {:?}", e)));
                 }
             });
         }
 
+        { // on export result
+            let searcher_msg_sender_clone = searcher_msg_sender.clone();
+            search_win.on_export_result(move |format, to_file| {
+                let _ = searcher_msg_sender_clone.send(SearcherMessage::Export(format, to_file));
+            });
+        }
+
+        { // on open duplicates
+            let duplicate_win_clone = duplicate_finder.duplicate_win.as_weak();
+            search_win.on_open_duplicates(move || {
+                if let Some(duplicate_win) = duplicate_win_clone.upgrade() {
+                    let _ = duplicate_win.show();
+                }
+            });
+        }
+
+        { // on open diagnostics
+            let diagnostics_win_clone = diagnostics.diagnostics_win.as_weak();
+            search_win.on_open_diagnostics(move || {
+                if let Some(diagnostics_win) = diagnostics_win_clone.upgrade() {
+                    let _ = diagnostics_win.show();
+                    diagnostics_win.invoke_refresh();
+                }
+            });
+        }
+
+        { // on open snapshots
+            let snapshot_win_clone = snapshot_manager.snapshot_win.as_weak();
+            search_win.on_open_snapshots(move || {
+                if let Some(snapshot_win) = snapshot_win_clone.upgrade() {
+                    let _ = snapshot_win.show();
+                }
+            });
+        }
+
+        { // on open disk usage
+            let disk_usage_clone = disk_usage.clone();
+            search_win.on_open_disk_usage(move || {
+                disk_usage_clone.show();
+            });
+        }
+
+        { // on open recent changes
+            let recent_changes_clone = recent_changes.clone();
+            search_win.on_open_recent(move || {
+                recent_changes_clone.show();
+            });
+        }
+
         Ok(Searcher {
             search_win,
+            _duplicate_finder: duplicate_finder,
+            _diagnostics: diagnostics,
+            _snapshot_manager: snapshot_manager,
+            _disk_usage: disk_usage,
+            _recent_changes: recent_changes,
             searcher_msg_sender,
         })
     }
+}
+
+// Put a query into the search box and search it, the cursor after its end
+fn set_query(search_win: &SearchWindow, query: String) {
+    search_win.set_query(query.clone().into());
+    search_win.invoke_move_cursor_to_end();
+    search_win.invoke_query_change(query.into());
+}
+
+// Open the action menu of a row, only files and folders have one.
+// The menu keeps the path, rows may be replaced by a late result while it is open.
+fn open_menu(search_win: &SearchWindow, providers: &Providers, id: i32) {
+    if id < 0 { return; }
+    let Some(path) = providers.file_path(id as usize) else { return; };
+    let name = std::path::Path::new(&path).file_name().map_or(path.clone(), |name| name.to_string_lossy().to_string());
+    search_win.set_menu_active(0);
+    search_win.set_menu_name(name.into());
+    search_win.set_menu_path(path.into());
 }
\ No newline at end of file
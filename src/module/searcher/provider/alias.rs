@@ -0,0 +1,115 @@
+use crate::core::application::app_config::{AppConfig, Alias};
+use crate::ui::SearchResultType;
+use super::{ResultProvider, ResultSink, ResultItem, ResultAction};
+
+const EXACT_SCORE: i32 = 2000; // the keyword was typed in full, above everything else
+const PREFIX_SCORE: i32 = 900; // the keyword is being typed, above apps
+const PLACEHOLDER: &str = "{query}";
+
+// The keywords defined in the search settings: urls, folders and commands
+pub struct AliasProvider;
+
+impl AliasProvider {
+    pub fn new() -> AliasProvider {
+        AliasProvider
+    }
+
+    fn to_result_item(alias: &Alias, rest: &str, score: i32) -> ResultItem {
+        if alias.keyword.starts_with('>') {
+            let command_line = if alias.target.contains(PLACEHOLDER) { alias.target.replace(PLACEHOLDER, rest) }
+                else if rest.is_empty() { alias.target.clone() }
+                else { format!("{} {}", alias.target, rest) };
+            // the typed rest is only text for the command, cmd must not see "&" or "|" in it as its own
+            let template = if alias.target.contains(PLACEHOLDER) || rest.is_empty() { alias.target.clone() }
+                else { format!("{} {}", alias.target, PLACEHOLDER) };
+            let run_line = fill_cmd_template(&template, rest);
+            return ResultItem {
+                title: command_line,
+                subtitle: alias.dir.clone(),
+                icon_path: String::new(),
+                result_type: SearchResultType::System,
+                score,
+                action: ResultAction::Run(run_line, alias.dir.clone()),
+            };
+        }
+
+        let is_url = alias.target.contains("://");
+        let target = if is_url { alias.target.replace(PLACEHOLDER, &encode_url(rest)) } else { alias.target.replace(PLACEHOLDER, rest) };
+        let is_folder = alias.is_folder;
+        ResultItem {
+            title: target.clone(),
+            subtitle: alias.keyword.clone(),
+            icon_path: if is_folder { target.clone() } else { String::new() },
+            result_type: if is_folder { SearchResultType::Folder } else { SearchResultType::System },
+            score,
+            action: ResultAction::Open(target),
+        }
+    }
+}
+
+impl ResultProvider for AliasProvider {
+    fn query(&mut self, query: &str, sink: &ResultSink) {
+        let typed = query.trim_start();
+        if typed.is_empty() { return; }
+
+        let aliases = AppConfig::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get_aliases();
+        let items = aliases.iter().filter_map(|alias| {
+            if let Some(rest) = strip_keyword(typed, &alias.keyword) {
+                return Some(Self::to_result_item(alias, rest, EXACT_SCORE));
+            }
+            let is_prefix = alias.keyword.len() > typed.len()
+                && alias.keyword.get(..typed.len()).is_some_and(|head| head.eq_ignore_ascii_case(typed));
+            if is_prefix { Some(Self::to_result_item(alias, "", PREFIX_SCORE)) } else { None }
+        }).collect::<Vec<_>>();
+
+        if !items.is_empty() { sink.publish(query, items, false); }
+    }
+}
+
+// The rest of a query starting with the keyword, "g cats" -> "cats", while "gx" does not start with "g".
+// A keyword ending in a symbol, like ">", needs no space after it.
+fn strip_keyword<'a>(typed: &'a str, keyword: &str) -> Option<&'a str> {
+    if keyword.is_empty() { return None; }
+    let head = typed.get(..keyword.len())?;
+    if !head.eq_ignore_ascii_case(keyword) { return None; }
+    let rest = &typed[keyword.len()..];
+    let separated = rest.is_empty() || rest.starts_with(char::is_whitespace) || !keyword.ends_with(char::is_alphanumeric);
+    if separated { Some(rest.trim()) } else { None }
+}
+
+// Put the text in for each placeholder, escaped for whether the placeholder is inside quotes of the template
+fn fill_cmd_template(template: &str, text: &str) -> String {
+    let mut line = String::with_capacity(template.len() + text.len());
+    let mut quoted = false;
+    for (index, part) in template.split(PLACEHOLDER).enumerate() {
+        if index > 0 { line += &escape_cmd(text, quoted); }
+        line += part;
+        quoted ^= part.matches('"').count() % 2 == 1;
+    }
+    line
+}
+
+// Escape the characters cmd treats specially, the words stay apart as typed.
+// Inside quotes cmd keeps carets as they are, only a quote would end them there and is doubled.
+// A caret does not stop %VAR% either, a percent sign becomes "%%cd:~,%" which cmd reads as "%" and an empty part of %cd%.
+fn escape_cmd(text: &str, quoted: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '%' => escaped += "%%cd:~,",
+            '"' if quoted => escaped.push('"'),
+            '^' | '&' | '|' | '<' | '>' | '(' | ')' | '"' | '!' if !quoted => escaped.push('^'),
+            _ => {},
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Percent-encode the text put into a url template
+fn encode_url(text: &str) -> String {
+    text.bytes().map(|byte| {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') { (byte as char).to_string() }
+        else { format!("%{:02X}", byte) }
+    }).collect()
+}
@@ -0,0 +1,218 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::ui::SearchResultType;
+use super::{ResultProvider, ResultSink, ResultItem, ResultAction, is_provider_query};
+use super::super::content::CONTENT_PREFIX;
+
+const MAX_RESULTS: usize = 8;
+const BASE_SCORE: i32 = i8::MAX as i32 + 1; // above every file rank
+const RESCAN_INTERVAL: Duration = Duration::from_secs(300);
+const MAX_DEPTH: usize = 4; // folders of the start menu
+
+// A program found in the start menu or as a desktop entry
+#[derive(Clone)]
+struct App {
+    name: String,
+    name_lower: String,
+    keywords: Vec<String>, // lowercased
+    icon: String, // a file whose icon is shown
+    target: String, // the shortcut or desktop entry, opened to launch the program
+}
+
+#[derive(Default)]
+struct AppList {
+    apps: Vec<App>,
+    scanned: Option<Instant>,
+}
+
+// Programs by name, ranked above the files of the index
+pub struct AppProvider {
+    list: Arc<Mutex<AppList>>,
+    scanning: Arc<AtomicBool>,
+}
+
+impl AppProvider {
+    pub fn new() -> AppProvider {
+        let app_provider = AppProvider { list: Arc::new(Mutex::new(AppList::default())), scanning: Arc::new(AtomicBool::new(false)) };
+        app_provider.rescan();
+        app_provider
+    }
+
+    // Scan the shortcut folders again in the background, programs come and go while rotor runs
+    fn rescan(&self) {
+        if self.scanning.swap(true, Ordering::AcqRel) { return; }
+
+        let (list, scanning) = (self.list.clone(), self.scanning.clone());
+        std::thread::spawn(move || {
+            let mut apps = Vec::new();
+            for dir in shortcut_dirs() {
+                collect_apps(&dir, 0, &mut apps);
+            }
+            apps.sort_by(|a, b| a.name_lower.cmp(&b.name_lower));
+            apps.dedup_by(|a, b| a.name_lower == b.name_lower); // the same program for all users and the current one
+
+            let mut list = list.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            list.apps = apps;
+            list.scanned = Some(Instant::now());
+            scanning.store(false, Ordering::Release);
+        });
+    }
+
+    // How well an app matches, None if it does not
+    fn score(app: &App, query_lower: &str) -> Option<i32> {
+        let quality = if app.name_lower.starts_with(query_lower) { 60 }
+            else if app.name_lower.split_whitespace().any(|word| word.starts_with(query_lower)) { 40 }
+            else if app.name_lower.contains(query_lower) { 20 }
+            else if app.keywords.iter().any(|keyword| keyword.starts_with(query_lower)) { 10 }
+            else { return None; };
+        Some(BASE_SCORE + quality + (30 - app.name.chars().count() as i32).max(0)) // shorter names first
+    }
+}
+
+impl ResultProvider for AppProvider {
+    fn query(&mut self, query: &str, sink: &ResultSink) {
+        let query_lower = query.trim().to_lowercase();
+        if query_lower.is_empty() || query_lower.starts_with(CONTENT_PREFIX) || is_provider_query(&query_lower) {
+            let stale = self.list.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+                .scanned.is_some_and(|scanned| scanned.elapsed() > RESCAN_INTERVAL);
+            if stale { self.rescan(); }
+            return;
+        }
+
+        let mut found = self.list.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+            .apps.iter()
+            .filter_map(|app| Some((Self::score(app, &query_lower)?, app.clone())))
+            .collect::<Vec<_>>();
+        found.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+
+        let items = found.into_iter().take(MAX_RESULTS).map(|(score, app)| ResultItem {
+            title: app.name,
+            subtitle: app.target.clone(),
+            icon_path: app.icon,
+            result_type: SearchResultType::Application,
+            score,
+            action: ResultAction::Open(app.target),
+        }).collect();
+        sink.publish(query, items, false);
+    }
+}
+
+// The folders holding the shortcuts of installed programs
+#[cfg(windows)]
+fn shortcut_dirs() -> Vec<PathBuf> {
+    ["ProgramData", "APPDATA"].iter()
+        .filter_map(|var| std::env::var_os(var))
+        .map(|root| PathBuf::from(root).join("Microsoft\\Windows\\Start Menu\\Programs"))
+        .collect()
+}
+
+#[cfg(not(windows))]
+fn shortcut_dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS").unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string());
+    data_home.into_iter()
+        .chain(data_dirs.split(':').filter(|dir| !dir.is_empty()).map(PathBuf::from))
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+fn collect_apps(dir: &Path, depth: usize, apps: &mut Vec<App>) {
+    let Ok(entries) = fs::read_dir(dir) else { return; };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth < MAX_DEPTH { collect_apps(&path, depth + 1, apps); }
+            continue;
+        }
+        let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let app = match extension.as_str() {
+            "lnk" => read_shortcut(&path),
+            #[cfg(not(windows))]
+            "desktop" => read_desktop_entry(&path),
+            _ => None,
+        };
+        if let Some(app) = app { apps.push(app); }
+    }
+}
+
+// A start menu shortcut, named after its file, with the words of its comment as keywords
+fn read_shortcut(path: &Path) -> Option<App> {
+    let name = path.file_stem()?.to_string_lossy().to_string();
+    if name.to_lowercase().contains("uninstall") { return None; }
+    let target = path.to_string_lossy().to_string();
+    let keywords = fs::read(path).ok()
+        .and_then(|data| shortcut_comment(&data))
+        .map(|comment| comment.to_lowercase().split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+    Some(App { name_lower: name.to_lowercase(), name, keywords, icon: target.clone(), target })
+}
+
+// The description string of a shell link (MS-SHLLINK), stored after the id list and the link info
+fn shortcut_comment(data: &[u8]) -> Option<String> {
+    const HEADER_SIZE: usize = 0x4C;
+    const HAS_ID_LIST: u32 = 0x01;
+    const HAS_LINK_INFO: u32 = 0x02;
+    const HAS_NAME: u32 = 0x04;
+    const IS_UNICODE: u32 = 0x80;
+
+    let read_u16 = |offset: usize| Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?) as usize);
+    let read_u32 = |offset: usize| Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?));
+
+    if read_u32(0)? as usize != HEADER_SIZE { return None; }
+    let flags = read_u32(0x14)?;
+    if flags & HAS_NAME == 0 { return None; }
+
+    let mut offset = HEADER_SIZE;
+    if flags & HAS_ID_LIST != 0 { offset += 2 + read_u16(offset)?; }
+    if flags & HAS_LINK_INFO != 0 { offset += read_u32(offset)? as usize; }
+
+    let count = read_u16(offset)?;
+    offset += 2;
+    if flags & IS_UNICODE != 0 {
+        let units = data.get(offset..offset + count * 2)?
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect::<Vec<_>>();
+        Some(String::from_utf16_lossy(&units))
+    } else {
+        Some(String::from_utf8_lossy(data.get(offset..offset + count)?).to_string())
+    }
+}
+
+// An application of the freedesktop menu, hidden entries are left out
+#[cfg(not(windows))]
+fn read_desktop_entry(path: &Path) -> Option<App> {
+    let text = fs::read_to_string(path).ok()?;
+    let mut in_entry = false;
+    let (mut name, mut icon, mut keywords) = (None, String::new(), Vec::new());
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_entry { continue; }
+        let Some((key, value)) = line.split_once('=') else { continue; };
+        match key.trim() {
+            "Type" if value.trim() != "Application" => return None,
+            "NoDisplay" | "Hidden" if value.trim() == "true" => return None,
+            "Name" => name = Some(value.trim().to_string()),
+            "Icon" => icon = value.trim().to_string(),
+            "GenericName" | "Keywords" => keywords.extend(
+                value.to_lowercase().split(|c: char| c == ';' || c.is_whitespace()).filter(|keyword| !keyword.is_empty()).map(str::to_string)
+            ),
+            _ => {},
+        }
+    }
+
+    let name = name?;
+    let target = path.to_string_lossy().to_string();
+    // a themed icon name can not be shown, only an icon file
+    let icon = if Path::new(&icon).is_absolute() { icon } else { String::new() };
+    Some(App { name_lower: name.to_lowercase(), name, keywords, icon, target })
+}
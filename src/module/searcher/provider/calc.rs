@@ -0,0 +1,497 @@
+use crate::ui::SearchResultType;
+use super::{ResultProvider, ResultSink, ResultItem, ResultAction};
+
+const SCORE: i32 = 1000; // above apps and files, a computed value is what was asked for
+const MAX_DEPTH: usize = 256; // of nested parentheses and signs, deeper input is no calculation
+
+// What a unit measures, units convert only within the same dimension
+#[derive(Clone, Copy, PartialEq)]
+enum Dimension {
+    Length,
+    Mass,
+    Time,
+    Data,
+    Area,
+    Volume,
+    Speed,
+    Temperature,
+}
+
+// names, dimension, size in the base unit of the dimension; temperatures use kelvin with an offset instead
+const UNITS: &[(&[&str], Dimension, f64)] = &[
+    (&["m", "meter", "meters", "metre", "metres"], Dimension::Length, 1.),
+    (&["km", "kilometer", "kilometers"], Dimension::Length, 1000.),
+    (&["cm", "centimeter", "centimeters"], Dimension::Length, 0.01),
+    (&["mm", "millimeter", "millimeters"], Dimension::Length, 0.001),
+    (&["um", "µm", "micrometer"], Dimension::Length, 1e-6),
+    (&["nm", "nanometer"], Dimension::Length, 1e-9),
+    (&["mi", "mile", "miles"], Dimension::Length, 1609.344),
+    (&["yd", "yard", "yards"], Dimension::Length, 0.9144),
+    (&["ft", "foot", "feet"], Dimension::Length, 0.3048),
+    (&["in", "inch", "inches"], Dimension::Length, 0.0254),
+    (&["nmi"], Dimension::Length, 1852.),
+    (&["kg", "kilogram", "kilograms"], Dimension::Mass, 1.),
+    (&["g", "gram", "grams"], Dimension::Mass, 0.001),
+    (&["mg", "milligram", "milligrams"], Dimension::Mass, 1e-6),
+    (&["t", "ton", "tons", "tonne", "tonnes"], Dimension::Mass, 1000.),
+    (&["lb", "lbs", "pound", "pounds"], Dimension::Mass, 0.45359237),
+    (&["oz", "ounce", "ounces"], Dimension::Mass, 0.028349523125),
+    (&["ms", "millisecond", "milliseconds"], Dimension::Time, 0.001),
+    (&["s", "sec", "second", "seconds"], Dimension::Time, 1.),
+    (&["min", "minute", "minutes"], Dimension::Time, 60.),
+    (&["h", "hr", "hour", "hours"], Dimension::Time, 3600.),
+    (&["d", "day", "days"], Dimension::Time, 86400.),
+    (&["wk", "week", "weeks"], Dimension::Time, 604800.),
+    (&["yr", "year", "years"], Dimension::Time, 31557600.), // julian year
+    (&["bit", "bits"], Dimension::Data, 0.125),
+    (&["b", "byte", "bytes"], Dimension::Data, 1.),
+    (&["kb", "kib"], Dimension::Data, 1024.), // binary units as in the sizes shown elsewhere
+    (&["mb", "mib"], Dimension::Data, 1048576.),
+    (&["gb", "gib"], Dimension::Data, 1073741824.),
+    (&["tb", "tib"], Dimension::Data, 1099511627776.),
+    (&["m2", "m²"], Dimension::Area, 1.),
+    (&["km2", "km²"], Dimension::Area, 1e6),
+    (&["cm2", "cm²"], Dimension::Area, 1e-4),
+    (&["ft2", "ft²"], Dimension::Area, 0.09290304),
+    (&["mi2", "mi²"], Dimension::Area, 2589988.110336),
+    (&["ha", "hectare", "hectares"], Dimension::Area, 1e4),
+    (&["acre", "acres"], Dimension::Area, 4046.8564224),
+    (&["l", "liter", "liters", "litre", "litres"], Dimension::Volume, 1.),
+    (&["ml", "milliliter", "milliliters"], Dimension::Volume, 0.001),
+    (&["m3", "m³"], Dimension::Volume, 1000.),
+    (&["gal", "gallon", "gallons"], Dimension::Volume, 3.785411784), // us
+    (&["qt", "quart", "quarts"], Dimension::Volume, 0.946352946),
+    (&["pt", "pint", "pints"], Dimension::Volume, 0.473176473),
+    (&["cup", "cups"], Dimension::Volume, 0.2365882365),
+    (&["floz"], Dimension::Volume, 0.0295735295625),
+    (&["m/s"], Dimension::Speed, 1.),
+    (&["km/h", "kmh", "kph"], Dimension::Speed, 1. / 3.6),
+    (&["mph"], Dimension::Speed, 0.44704),
+    (&["kn", "knot", "knots"], Dimension::Speed, 1852. / 3600.),
+    (&["c", "°c", "celsius"], Dimension::Temperature, 0.),
+    (&["f", "°f", "fahrenheit"], Dimension::Temperature, 0.),
+    (&["k", "kelvin"], Dimension::Temperature, 0.),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Base {
+    Hex,
+    Bin,
+    Oct,
+    Dec,
+}
+
+// Computes "=expression" and converts "3 km in mi" or "255 in hex", Enter copies the value
+pub struct CalcProvider;
+
+impl CalcProvider {
+    pub fn new() -> CalcProvider {
+        CalcProvider
+    }
+}
+
+impl ResultProvider for CalcProvider {
+    fn query(&mut self, query: &str, sink: &ResultSink) {
+        let Some((title, value, subtitle)) = calculate(query.trim()) else { return; };
+        sink.publish(query, vec![ResultItem {
+            title,
+            subtitle,
+            icon_path: String::new(),
+            result_type: SearchResultType::System,
+            score: SCORE,
+            action: ResultAction::Copy(value),
+        }], false);
+    }
+}
+
+// The shown result, the value to copy and what was computed, None if the query is not a calculation
+fn calculate(query: &str) -> Option<(String, String, String)> {
+    let (expression, explicit) = match query.strip_prefix('=') {
+        Some(expression) => (expression.trim(), true),
+        None => (query, false),
+    };
+    if expression.is_empty() { return None; }
+
+    // "<value> [unit] in|to|as <unit or base>"
+    let conversion = [" in ", " to ", " as "].iter()
+        .filter_map(|separator| expression.to_ascii_lowercase().rfind(separator).map(|index| (index, separator.len())))
+        .max_by_key(|(index, _)| *index);
+    if let Some((index, separator_len)) = conversion {
+        let (left, target) = (expression[..index].trim(), expression[index + separator_len..].trim());
+        if let Some(result) = convert(left, target) { return Some(result); }
+    }
+
+    if !explicit { return None; }
+    let value = evaluate(expression)?;
+    let text = format_number(value)?;
+    Some((text.clone(), text, expression.to_string()))
+}
+
+fn convert(left: &str, target: &str) -> Option<(String, String, String)> {
+    let target_lower = target.to_lowercase();
+    if let Some(base) = parse_base(&target_lower) {
+        let value = evaluate(left)?;
+        let text = format_base(value, base)?;
+        return Some((text.clone(), text, left.to_string()));
+    }
+
+    let (to_dimension, to_factor) = find_unit(&target_lower)?;
+    // the longest unit at the end, the rest is the amount
+    let (unit_start, (from_dimension, from_factor)) = left.char_indices()
+        .find_map(|(index, _)| Some((index, find_unit(&left[index..].trim().to_lowercase())?)))?;
+    let (amount, unit) = (left[..unit_start].trim(), left[unit_start..].trim().to_lowercase());
+    if from_dimension != to_dimension { return None; }
+
+    let amount = if amount.is_empty() { 1. } else { evaluate(amount)? };
+    let value = if from_dimension == Dimension::Temperature {
+        from_kelvin(to_kelvin(amount, &unit)?, &target_lower)?
+    } else {
+        amount * from_factor / to_factor
+    };
+    let text = format_number(value)?;
+    Some((format!("{} {}", text, target), text, format!("{} {}", format_number(amount)?, left[unit_start..].trim())))
+}
+
+fn find_unit(name: &str) -> Option<(Dimension, f64)> {
+    UNITS.iter()
+        .find(|(names, _, _)| names.contains(&name))
+        .map(|(_, dimension, factor)| (*dimension, *factor))
+}
+
+fn to_kelvin(value: f64, unit: &str) -> Option<f64> {
+    match unit.trim_start_matches('°').chars().next()? {
+        'c' => Some(value + 273.15),
+        'f' => Some((value - 32.) * 5. / 9. + 273.15),
+        'k' => Some(value),
+        _ => None,
+    }
+}
+
+fn from_kelvin(value: f64, unit: &str) -> Option<f64> {
+    match unit.trim_start_matches('°').chars().next()? {
+        'c' => Some(value - 273.15),
+        'f' => Some((value - 273.15) * 9. / 5. + 32.),
+        'k' => Some(value),
+        _ => None,
+    }
+}
+
+fn parse_base(name: &str) -> Option<Base> {
+    match name {
+        "hex" | "hexadecimal" => Some(Base::Hex),
+        "bin" | "binary" => Some(Base::Bin),
+        "oct" | "octal" => Some(Base::Oct),
+        "dec" | "decimal" => Some(Base::Dec),
+        _ => None,
+    }
+}
+
+fn format_base(value: f64, base: Base) -> Option<String> {
+    if value.fract() != 0. || value.abs() >= 9007199254740992. { return None; } // integers exact in a f64
+    let (sign, magnitude) = (if value < 0. { "-" } else { "" }, value.abs() as u64);
+    Some(match base {
+        Base::Hex => format!("{}0x{:X}", sign, magnitude),
+        Base::Bin => format!("{}0b{:b}", sign, magnitude),
+        Base::Oct => format!("{}0o{:o}", sign, magnitude),
+        Base::Dec => format!("{}{}", sign, magnitude),
+    })
+}
+
+// Whole numbers as they are, others with ten significant digits
+fn format_number(value: f64) -> Option<String> {
+    if !value.is_finite() { return None; }
+    if value.fract() == 0. && value.abs() < 1e15 { return Some(format!("{}", value as i64)); }
+    let magnitude = value.abs().log10().floor() as i32;
+    if !(-6..15).contains(&magnitude) {
+        let text = format!("{:.9e}", value);
+        let (mantissa, exponent) = text.split_once('e')?;
+        return Some(format!("{}e{}", trim_zeros(mantissa), exponent));
+    }
+    Some(trim_zeros(&format!("{:.*}", (9 - magnitude).max(0) as usize, value)).to_string())
+}
+
+fn trim_zeros(text: &str) -> &str {
+    if !text.contains('.') { return text; }
+    text.trim_end_matches('0').trim_end_matches('.')
+}
+
+fn evaluate(expression: &str) -> Option<f64> {
+    let mut parser = Parser { chars: expression.chars().collect(), position: 0, depth: 0 };
+    let value = parser.expression()?;
+    parser.skip_spaces();
+    if parser.position != parser.chars.len() { return None; }
+    Some(value.value)
+}
+
+// A value and whether it was written as a percentage, "200 + 10%" adds ten percent of 200
+#[derive(Clone, Copy)]
+struct Value {
+    value: f64,
+    percent: bool,
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Value {
+        Value { value, percent: false }
+    }
+}
+
+// A recursive descent parser evaluating while it reads:
+// expression = term (("+" | "-") term)*
+// term = unary (("*" | "/" | "mod") unary)*
+// unary = ("-" | "+") unary | power
+// power = postfix ("^" unary)?
+// postfix = primary ("%" | "!")*
+// primary = number | "(" expression ")" | name | name "(" expression ("," expression)* ")"
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    depth: usize, // of the unary calls running, every nesting goes through one
+}
+
+impl Parser {
+    fn skip_spaces(&mut self) {
+        while self.chars.get(self.position).is_some_and(|c| c.is_whitespace()) { self.position += 1; }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_spaces();
+        self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        self.skip_spaces();
+        let end = self.position + word.chars().count();
+        let matches = self.chars.get(self.position..end).is_some_and(|chars| chars.iter().copied().eq(word.chars()))
+            && !self.chars.get(end).is_some_and(|c| c.is_alphanumeric());
+        if matches { self.position = end; }
+        matches
+    }
+
+    fn expression(&mut self) -> Option<Value> {
+        let mut left = self.term()?;
+        loop {
+            let sign = if self.eat('+') { 1. } else if self.eat('-') { -1. } else { return Some(left); };
+            let right = self.term()?;
+            let right = if right.percent { left.value * right.value } else { right.value };
+            left = (left.value + sign * right).into();
+        }
+    }
+
+    fn term(&mut self) -> Option<Value> {
+        let mut left = self.unary()?;
+        loop {
+            if self.eat('*') || self.eat('×') { left = (left.value * self.unary()?.value).into(); }
+            else if self.eat('/') || self.eat('÷') { left = (left.value / self.unary()?.value).into(); }
+            else if self.eat_word("mod") { left = (left.value.rem_euclid(self.unary()?.value)).into(); }
+            else { return Some(left); }
+        }
+    }
+
+    fn unary(&mut self) -> Option<Value> {
+        if self.depth == MAX_DEPTH { return None; } // before the stack runs out
+        self.depth += 1;
+        let value = self.signed();
+        self.depth -= 1;
+        value
+    }
+
+    fn signed(&mut self) -> Option<Value> {
+        if self.eat('-') {
+            let value = self.unary()?;
+            return Some(Value { value: -value.value, ..value });
+        }
+        if self.eat('+') { return self.unary(); }
+        self.power()
+    }
+
+    fn power(&mut self) -> Option<Value> {
+        let base = self.postfix()?;
+        if self.eat('^') { return Some(base.value.powf(self.unary()?.value).into()); }
+        Some(base)
+    }
+
+    fn postfix(&mut self) -> Option<Value> {
+        let mut value = self.primary()?;
+        loop {
+            if self.eat('%') { value = Value { value: value.value / 100., percent: true }; }
+            else if self.eat('!') { value = factorial(value.value)?.into(); }
+            else { return Some(value); }
+        }
+    }
+
+    fn primary(&mut self) -> Option<Value> {
+        let c = self.peek()?;
+        if self.eat('(') {
+            let value = self.expression()?;
+            return if self.eat(')') { Some(value.value.into()) } else { None };
+        }
+        if c.is_ascii_digit() || c == '.' { return self.number().map(Value::from); }
+        if c.is_alphabetic() { return self.call().map(Value::from); }
+        None
+    }
+
+    fn number(&mut self) -> Option<f64> {
+        let radix = match (self.chars.get(self.position), self.chars.get(self.position + 1)) {
+            (Some('0'), Some('x' | 'X')) => 16,
+            (Some('0'), Some('b' | 'B')) => 2,
+            (Some('0'), Some('o' | 'O')) => 8,
+            _ => 10,
+        };
+        if radix != 10 {
+            self.position += 2;
+            let start = self.position;
+            while self.chars.get(self.position).is_some_and(|c| c.is_digit(radix) || *c == '_') { self.position += 1; }
+            let digits = self.chars[start..self.position].iter().filter(|c| **c != '_').collect::<String>();
+            return u64::from_str_radix(&digits, radix).ok().map(|value| value as f64);
+        }
+
+        let start = self.position;
+        while self.chars.get(self.position).is_some_and(|c| c.is_ascii_digit() || *c == '.' || *c == '_') { self.position += 1; }
+        // an exponent, only when digits follow
+        if self.chars.get(self.position).is_some_and(|c| *c == 'e' || *c == 'E') {
+            let mut end = self.position + 1;
+            if self.chars.get(end).is_some_and(|c| *c == '+' || *c == '-') { end += 1; }
+            if self.chars.get(end).is_some_and(|c| c.is_ascii_digit()) {
+                self.position = end;
+                while self.chars.get(self.position).is_some_and(|c| c.is_ascii_digit()) { self.position += 1; }
+            }
+        }
+        self.chars[start..self.position].iter().filter(|c| **c != '_').collect::<String>().parse().ok()
+    }
+
+    // a constant or a function
+    fn call(&mut self) -> Option<f64> {
+        let start = self.position;
+        while self.chars.get(self.position).is_some_and(|c| c.is_alphanumeric()) { self.position += 1; }
+        let name = self.chars[start..self.position].iter().collect::<String>().to_lowercase();
+
+        if !self.eat('(') {
+            return match name.as_str() {
+                "pi" | "π" => Some(std::f64::consts::PI),
+                "tau" => Some(std::f64::consts::TAU),
+                "e" => Some(std::f64::consts::E),
+                _ => None,
+            };
+        }
+        let mut args = vec![self.expression()?.value];
+        while self.eat(',') { args.push(self.expression()?.value); }
+        if !self.eat(')') { return None; }
+
+        let value = match (name.as_str(), args.as_slice()) {
+            ("min", [_, ..]) => args.iter().copied().fold(f64::INFINITY, f64::min),
+            ("max", [_, ..]) => args.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            ("pow", [base, exponent]) => base.powf(*exponent),
+            ("log", [value, base]) => value.log(*base),
+            (_, [x]) => match name.as_str() {
+                "sqrt" => x.sqrt(),
+                "cbrt" => x.cbrt(),
+                "abs" => x.abs(),
+                "floor" => x.floor(),
+                "ceil" => x.ceil(),
+                "round" => x.round(),
+                "ln" => x.ln(),
+                "log" | "lg" => x.log10(),
+                "log2" => x.log2(),
+                "exp" => x.exp(),
+                "sin" => x.sin(),
+                "cos" => x.cos(),
+                "tan" => x.tan(),
+                "asin" => x.asin(),
+                "acos" => x.acos(),
+                "atan" => x.atan(),
+                "sinh" => x.sinh(),
+                "cosh" => x.cosh(),
+                "tanh" => x.tanh(),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+fn factorial(value: f64) -> Option<f64> {
+    if value < 0. || value.fract() != 0. || value > 170. { return None; }
+    Some((1..=value as u64).map(|n| n as f64).product())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::calculate;
+
+    fn value(query: &str) -> Option<String> {
+        calculate(query).map(|(_, value, _)| value)
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(value("=1+2*3"), Some("7".to_string()));
+        assert_eq!(value("=(1+2)*3"), Some("9".to_string()));
+        assert_eq!(value("=2^3^2"), Some("512".to_string()));
+        assert_eq!(value("=-2^2"), Some("-4".to_string()));
+        assert_eq!(value("=10 mod 4 + 1"), Some("3".to_string()));
+        assert_eq!(value("=3!+1"), Some("7".to_string()));
+        assert_eq!(value("=1/3"), Some("0.3333333333".to_string()));
+    }
+
+    #[test]
+    fn percentages() {
+        assert_eq!(value("=200 + 10%"), Some("220".to_string()));
+        assert_eq!(value("=200 - 10%"), Some("180".to_string()));
+        assert_eq!(value("=200 * 10%"), Some("20".to_string()));
+        assert_eq!(value("=50%"), Some("0.5".to_string()));
+    }
+
+    #[test]
+    fn bases() {
+        assert_eq!(value("255 in hex"), Some("0xFF".to_string()));
+        assert_eq!(value("10 to bin"), Some("0b1010".to_string()));
+        assert_eq!(value("0x1F as dec"), Some("31".to_string()));
+        assert_eq!(value("-8 in oct"), Some("-0o10".to_string()));
+        assert_eq!(value("1.5 in hex"), None);
+    }
+
+    #[test]
+    fn units() {
+        assert_eq!(calculate("1 km in m"), Some(("1000 m".to_string(), "1000".to_string(), "1 km".to_string())));
+        assert_eq!(value("2 gb to mb"), Some("2048".to_string()));
+        assert_eq!(value("100 c to f"), Some("212".to_string()));
+        assert_eq!(value("kg in g"), Some("1000".to_string()));
+        assert_eq!(value("1 kg in m"), None); // other dimensions
+    }
+
+    #[test]
+    fn conversion_split() {
+        // the last separator splits, "in" may be a unit itself
+        assert_eq!(value("1 in in cm"), Some("2.54".to_string()));
+        assert_eq!(value("2*3 in hex"), Some("0x6".to_string()));
+        assert_eq!(value("=1 + 1 to bin"), Some("0b10".to_string()));
+    }
+
+    #[test]
+    fn no_calculation() {
+        assert_eq!(value("1+2"), None); // plain queries need "="
+        assert_eq!(value("="), None);
+        assert_eq!(value("=1+"), None);
+        assert_eq!(value("=foo(1)"), None);
+        assert_eq!(value("notes to self"), None);
+    }
+
+    #[test]
+    fn depth_limit() {
+        let nested = format!("={}1{}", "(".repeat(100), ")".repeat(100));
+        assert_eq!(value(&nested), Some("1".to_string()));
+        let deep = format!("={}1{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert_eq!(value(&deep), None);
+        assert_eq!(value(&format!("={}1", "-".repeat(100_000))), None);
+    }
+}
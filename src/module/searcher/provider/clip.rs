@@ -0,0 +1,63 @@
+use crate::core::application::app_config::AppConfig;
+use crate::ui::SearchResultType;
+use super::{ResultProvider, ResultSink, ResultItem, ResultAction};
+use super::super::clip_history::{ClipHistory, ClipContent};
+
+pub const CLIP_PREFIX: &str = "clip:";
+const MAX_RESULTS: usize = 100;
+const MAX_TITLE_CHARS: usize = 80;
+const BASE_SCORE: i32 = 3000; // the only results of a clip: query, newest first
+
+// The clipboard history, choosing an entry puts it back on the clipboard
+pub struct ClipProvider;
+
+impl ClipProvider {
+    pub fn new() -> ClipProvider {
+        ClipProvider
+    }
+}
+
+impl ResultProvider for ClipProvider {
+    fn query(&mut self, query: &str, sink: &ResultSink) {
+        let Some(filter) = query.strip_prefix(CLIP_PREFIX) else { return; };
+        if !AppConfig::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get_clip_history() { return; }
+
+        let entries = ClipHistory::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).search(filter, MAX_RESULTS);
+        let items = entries.into_iter().enumerate().map(|(index, entry)| {
+            let time = format_time(entry.time);
+            match &entry.content {
+                ClipContent::Text(text) => {
+                    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+                    let mut title = lines.next().unwrap_or_default().chars().take(MAX_TITLE_CHARS).collect::<String>();
+                    if lines.next().is_some() || title.len() < text.trim().len() { title += " …"; }
+                    ResultItem {
+                        title,
+                        subtitle: format!("{}  ·  {}", time, text.chars().count()),
+                        icon_path: String::new(),
+                        result_type: SearchResultType::System,
+                        score: BASE_SCORE - index as i32,
+                        action: ResultAction::Copy(text.clone()),
+                    }
+                },
+                ClipContent::Image { width, height } => {
+                    let image_path = entry.image_path().to_string_lossy().to_string();
+                    ResultItem {
+                        title: format!("{} × {}", width, height),
+                        subtitle: time,
+                        icon_path: image_path.clone(),
+                        result_type: SearchResultType::System,
+                        score: BASE_SCORE - index as i32,
+                        action: ResultAction::CopyImage(image_path),
+                    }
+                },
+            }
+        }).collect();
+        sink.publish(query, items, false);
+    }
+}
+
+fn format_time(time: i64) -> String {
+    chrono::DateTime::from_timestamp(time, 0)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
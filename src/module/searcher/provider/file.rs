@@ -0,0 +1,42 @@
+use std::sync::mpsc;
+
+use crate::ui::SearchResultType;
+use super::{ResultProvider, ResultSink, ResultItem, ResultAction, is_provider_query};
+use super::super::{SearcherMessage, archive};
+use super::super::volume::SearchResultItem;
+
+// The files of the index, found by the FileData thread which publishes through its own sink
+pub struct FileProvider {
+    msg_sender: mpsc::Sender<SearcherMessage>,
+}
+
+impl FileProvider {
+    pub fn new(msg_sender: mpsc::Sender<SearcherMessage>) -> FileProvider {
+        FileProvider { msg_sender }
+    }
+
+    pub fn to_result_item(item: SearchResultItem) -> ResultItem {
+        let full_path = item.path.clone() + item.file_name.as_str();
+        ResultItem {
+            title: item.file_name,
+            subtitle: item.path,
+            icon_path: full_path.clone(),
+            result_type: if archive::is_entry_path(&full_path) { SearchResultType::ArchiveEntry } else { SearchResultType::File },
+            score: item.rank as i32,
+            action: ResultAction::Open(full_path),
+        }
+    }
+}
+
+impl ResultProvider for FileProvider {
+    fn query(&mut self, query: &str, _sink: &ResultSink) {
+        if is_provider_query(query) { return; } // not a file name
+        let _ = self.msg_sender.send(SearcherMessage::Find(query.to_string()));
+    }
+
+    // the next page, FileData continues a query it is asked for again
+    fn more(&mut self, query: &str, _sink: &ResultSink) {
+        if is_provider_query(query) { return; }
+        let _ = self.msg_sender.send(SearcherMessage::Find(query.to_string()));
+    }
+}
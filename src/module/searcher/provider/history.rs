@@ -0,0 +1,34 @@
+use crate::core::application::app_config::AppConfig;
+use crate::ui::SearchResultType;
+use super::{ResultProvider, ResultSink, ResultItem, ResultAction};
+use super::super::history::SearchHistory;
+
+const MAX_RESULTS: usize = 20;
+const BASE_SCORE: i32 = 3000; // the only results of an empty query
+
+// The recent queries while the search box is empty, choosing one searches it again
+pub struct HistoryProvider;
+
+impl HistoryProvider {
+    pub fn new() -> HistoryProvider {
+        HistoryProvider
+    }
+}
+
+impl ResultProvider for HistoryProvider {
+    fn query(&mut self, query: &str, sink: &ResultSink) {
+        if !query.is_empty() { return; }
+        if !AppConfig::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get_search_history() { return; }
+
+        let queries = SearchHistory::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).recent(MAX_RESULTS);
+        let items = queries.into_iter().enumerate().map(|(index, recent_query)| ResultItem {
+            title: recent_query.clone(),
+            subtitle: String::new(),
+            icon_path: String::new(),
+            result_type: SearchResultType::System,
+            score: BASE_SCORE - index as i32,
+            action: ResultAction::Query(recent_query),
+        }).collect();
+        sink.publish(query, items, false);
+    }
+}
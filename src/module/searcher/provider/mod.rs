@@ -0,0 +1,258 @@
+mod file;
+mod app;
+mod calc;
+mod alias;
+mod clip;
+mod unicode;
+mod history;
+mod path;
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use arboard::{Clipboard, ImageData};
+use slint::{Model, VecModel};
+
+use crate::core::application::app_config::AppConfig;
+use crate::ui::{SearchResult_slint, SearchResultType};
+use crate::util::{file_util, log_util};
+use super::{archive, SearchWindow};
+use super::history::SearchHistory;
+pub use file::FileProvider;
+pub use app::AppProvider;
+pub use calc::CalcProvider;
+pub use alias::AliasProvider;
+pub use clip::ClipProvider;
+pub use unicode::UnicodeProvider;
+pub use history::HistoryProvider;
+pub use path::PathProvider;
+use clip::CLIP_PREFIX;
+use unicode::is_unicode_query;
+
+// Queries answered by a single provider, files and apps are not searched for them
+pub fn is_provider_query(query: &str) -> bool {
+    query.starts_with(CLIP_PREFIX) || is_unicode_query(query) || path::is_path_query(query)
+}
+
+// What happens when a result is chosen
+#[derive(Clone)]
+pub enum ResultAction {
+    Open(String), // a file, folder or url, opened with its default program
+    Copy(String), // text put on the clipboard
+    CopyImage(String), // an image file put on the clipboard
+    Run(String, String), // a command line and the folder it runs in
+    Query(String), // a query put into the search box, done by the search window
+}
+
+#[derive(Clone)]
+pub struct ResultItem {
+    pub title: String,
+    pub subtitle: String,
+    pub icon_path: String, // a file whose icon is shown, empty for none
+    pub result_type: SearchResultType,
+    pub score: i32, // higher first, file hits score their rank
+    pub action: ResultAction,
+}
+
+// A source of search results. Every query is asked of all providers, their items are merged by score.
+pub trait ResultProvider {
+    // Answer a query, right away or later from another thread through the sink
+    fn query(&mut self, query: &str, sink: &ResultSink);
+
+    // More items of the current query were asked for, e.g. the list was scrolled to the bottom
+    fn more(&mut self, _query: &str, _sink: &ResultSink) {}
+
+    // An item of this provider was chosen
+    fn activated(&mut self, _item: &ResultItem) {}
+}
+
+// The items of the current query, one list per provider
+#[derive(Default)]
+struct Results {
+    query: String,
+    lists: Vec<Vec<ResultItem>>,
+    merged: Vec<(usize, ResultItem)>, // provider and item, in the order of the rows
+}
+
+// Where a provider publishes its items, it can be sent to other threads
+#[derive(Clone)]
+pub struct ResultSink {
+    provider: usize,
+    results: Arc<Mutex<Results>>,
+    search_win: slint::Weak<SearchWindow>,
+}
+
+impl ResultSink {
+    // Replace the items of the provider for a query, dropped if the query changed meanwhile.
+    // reset_view scrolls the list back to the first row.
+    pub fn publish(&self, query: &str, items: Vec<ResultItem>, reset_view: bool) {
+        let rows = {
+            let mut results = self.results.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if results.query != query { return; }
+            if results.lists.len() <= self.provider { results.lists.resize(self.provider + 1, Vec::new()); }
+            results.lists[self.provider] = items;
+
+            let mut merged = results.lists.iter().enumerate()
+                .flat_map(|(provider, list)| list.iter().map(move |item| (provider, item.clone())))
+                .collect::<Vec<_>>();
+            merged.sort_by_key(|(_, item)| std::cmp::Reverse(item.score)); // stable, keeps the order of a provider
+            results.merged = merged;
+            results.merged.iter()
+                .map(|(_, item)| (item.title.clone(), item.subtitle.clone(), item.icon_path.clone(), item.result_type))
+                .collect::<Vec<_>>()
+        };
+        let completion = if path::is_path_query(query) {
+            let entries = rows.iter()
+                .filter(|(_, _, _, result_type)| matches!(result_type, SearchResultType::File | SearchResultType::Folder))
+                .map(|(title, _, _, result_type)| (title.clone(), *result_type == SearchResultType::Folder))
+                .collect::<Vec<_>>();
+            path::completion(query, &entries)
+        } else if is_provider_query(query) { None } else {
+            let file_names = rows.iter()
+                .filter(|(_, _, _, result_type)| *result_type == SearchResultType::File)
+                .map(|(title, _, _, _)| title.clone())
+                .collect::<Vec<_>>();
+            if AppConfig::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get_search_history() {
+                SearchHistory::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).completion(query, &file_names)
+            } else { super::history::complete_name(query, &file_names) }
+        };
+
+        let query = query.to_string();
+        self.search_win.upgrade_in_event_loop(move |search_win| {
+            if search_win.get_query() != query { return; }
+
+            let result_list = rows.into_iter().enumerate().map(|(id, (title, subtitle, icon_path, result_type))| {
+                SearchResult_slint {
+                    id: id as i32,
+                    icon: file_util::get_icon(&icon_path).unwrap_or_default(),
+                    filename: title.into(),
+                    path: subtitle.into(),
+                    result_type,
+                }
+            }).collect::<Vec<_>>();
+
+            search_win.set_completion(completion.unwrap_or_default().into());
+            if let Some(search_result_model) = search_win.get_search_result().as_any().downcast_ref::<VecModel<SearchResult_slint>>() {
+                search_result_model.set_vec(result_list);
+                if reset_view {
+                    search_win.set_viewport_y(0.);
+                    search_win.set_active_id(0);
+                }
+            }
+        }).unwrap_or_else(|e| log_util::log_error(format!("ResultSink publish: {}", e)));
+    }
+}
+
+// Fans the queries of the search window out to the providers and runs the chosen items
+pub struct Providers {
+    providers: Vec<Box<dyn ResultProvider>>,
+    results: Arc<Mutex<Results>>,
+    search_win: slint::Weak<SearchWindow>,
+}
+
+impl Providers {
+    pub fn new(search_win: slint::Weak<SearchWindow>) -> Providers {
+        Providers { providers: Vec::new(), results: Arc::new(Mutex::new(Results::default())), search_win }
+    }
+
+    // The sink of the provider added next, for providers that need it before they are created
+    pub fn next_sink(&self) -> ResultSink {
+        self.sink(self.providers.len())
+    }
+
+    pub fn add(&mut self, provider: Box<dyn ResultProvider>) {
+        self.providers.push(provider);
+    }
+
+    fn sink(&self, provider: usize) -> ResultSink {
+        ResultSink { provider, results: self.results.clone(), search_win: self.search_win.clone() }
+    }
+
+    // Ask all providers, or for more items when the query did not change
+    pub fn query(&mut self, query: &str) {
+        let same_query = {
+            let mut results = self.results.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if results.query == query && !query.is_empty() { true }
+            else {
+                *results = Results { query: query.to_string(), ..Default::default() };
+                false
+            }
+        };
+
+        if !same_query { self.sink(0).publish(query, Vec::new(), true); } // clear the rows of the last query
+        for provider in 0..self.providers.len() {
+            let sink = self.sink(provider);
+            if same_query { self.providers[provider].more(query, &sink); }
+            else { self.providers[provider].query(query, &sink); }
+        }
+    }
+
+    // Run the action of a row, return the item to tell what was done.
+    // The query is kept in the history while enabled, unless the row was a query of the history itself
+    // or the query went to a single provider like "clip:".
+    pub fn activate(&mut self, id: usize) -> Option<ResultItem> {
+        let (query, (provider, item)) = {
+            let results = self.results.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            (results.query.clone(), results.merged.get(id).cloned()?)
+        };
+        let enabled = AppConfig::global().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get_search_history();
+        if enabled && !is_provider_query(&query) && !matches!(item.action, ResultAction::Query(_)) {
+            SearchHistory::global()
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .record(&query)
+                .unwrap_or_else(|e| log_util::log_error(format!("Failed to save search history: {:?}", e)));
+        }
+        self.providers[provider].activated(&item);
+        Self::run(&item.action)
+            .unwrap_or_else(|e| log_util::log_error(format!("Failed to run result action: {:?}", e)));
+        Some(item)
+    }
+
+    // The file or folder of a row, None for urls, entries of archives and rows that are no files
+    pub fn file_path(&self, id: usize) -> Option<String> {
+        let results = self.results.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match &results.merged.get(id)?.1.action {
+            ResultAction::Open(path) if Path::new(path).exists() => Some(path.clone()),
+            _ => None,
+        }
+    }
+
+    // A file of the rows was renamed or removed, show it under its new path or drop its rows
+    pub fn file_changed(&self, old_path: &str, new_path: Option<&str>) {
+        let (query, lists) = {
+            let results = self.results.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            (results.query.clone(), results.lists.clone())
+        };
+        let is_changed = |item: &ResultItem| matches!(&item.action, ResultAction::Open(path) if path == old_path);
+        for (provider, list) in lists.into_iter().enumerate() {
+            if !list.iter().any(is_changed) { continue; }
+            let list = list.into_iter().filter_map(|item| {
+                if !is_changed(&item) { return Some(item); }
+                let new_path = new_path?;
+                let title = Path::new(new_path).file_name().map_or(item.title.clone(), |name| name.to_string_lossy().to_string());
+                Some(ResultItem { title, icon_path: new_path.to_string(), action: ResultAction::Open(new_path.to_string()), ..item })
+            }).collect();
+            self.sink(provider).publish(&query, list, false);
+        }
+    }
+
+    fn run(action: &ResultAction) -> Result<(), Box<dyn Error>> {
+        match action {
+            ResultAction::Open(path) => archive::open(path.clone())?,
+            ResultAction::Copy(text) => Clipboard::new()?.set_text(text.clone())?,
+            ResultAction::CopyImage(path) => {
+                let image = image::open(path)?.to_rgba8();
+                Clipboard::new()?.set_image(ImageData {
+                    width: image.width() as usize,
+                    height: image.height() as usize,
+                    bytes: Cow::from(image.into_raw()),
+                })?
+            },
+            ResultAction::Run(command_line, dir) => file_util::run_command(command_line.clone(), dir.clone())?,
+            ResultAction::Query(_) => {},
+        }
+        Ok(())
+    }
+}
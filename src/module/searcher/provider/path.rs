@@ -0,0 +1,139 @@
+use std::env;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::ui::SearchResultType;
+use super::{ResultProvider, ResultSink, ResultItem, ResultAction};
+use super::super::file_data::SharedVolume;
+use super::super::history;
+use super::super::volume::{FolderEntry, match_name};
+
+const MAX_RESULTS: usize = 200;
+const BASE_SCORE: i32 = 3000; // the only results of a path query
+
+// Whether a query is a folder to browse: "C:\", "\\server\share\", "~\" and "~/"
+pub fn is_path_query(query: &str) -> bool {
+    let bytes = query.as_bytes();
+    let drive = bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && is_separator(bytes[2] as char);
+    drive
+        || query.starts_with("\\\\")
+        || query.starts_with("~/")
+        || query.starts_with("~\\")
+}
+
+// The entries of the folder a path query is in, the part after the last separator filters them.
+// Folders are listed first, choosing one browses into it.
+pub struct PathProvider {
+    volumes: Arc<Mutex<Vec<SharedVolume>>>,
+    generation: Arc<AtomicUsize>, // counts the queries, a listing stops once a newer query came
+}
+
+impl PathProvider {
+    pub fn new(volumes: Arc<Mutex<Vec<SharedVolume>>>) -> PathProvider {
+        PathProvider { volumes, generation: Arc::new(AtomicUsize::new(0)) }
+    }
+}
+
+impl ResultProvider for PathProvider {
+    fn query(&mut self, query: &str, sink: &ResultSink) {
+        let generation = self.generation.fetch_add(1, Ordering::AcqRel) + 1;
+        if !is_path_query(query) { return; }
+
+        let volumes = self.volumes.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        let (sink, query, current) = (sink.clone(), query.to_string(), self.generation.clone());
+        std::thread::spawn(move || { // a network folder may take a while to answer
+            let (typed_dir, partial) = split_query(&query);
+            let separator = typed_dir.chars().last().unwrap_or('\\');
+            let dir = expand(typed_dir);
+            let partial_lower = partial.to_lowercase();
+
+            let is_current = || current.load(Ordering::Acquire) == generation;
+            let Some(entries) = list_folder(&volumes, &dir, &is_current) else { return; };
+            let mut entries = entries.into_iter()
+                .filter(|entry| match_name(&entry.name, &partial_lower).is_some())
+                .collect::<Vec<_>>();
+            entries.sort_by_cached_key(|entry| {
+                let name_lower = entry.name.to_lowercase();
+                (!name_lower.starts_with(&partial_lower), !entry.is_folder, name_lower)
+            });
+
+            let items = entries.into_iter().take(MAX_RESULTS).enumerate().map(|(index, entry)| {
+                let full_path = dir.clone() + entry.name.as_str();
+                ResultItem {
+                    title: entry.name.clone(),
+                    subtitle: dir.clone(),
+                    icon_path: full_path.clone(),
+                    result_type: if entry.is_folder { SearchResultType::Folder } else { SearchResultType::File },
+                    score: BASE_SCORE - index as i32,
+                    action: if entry.is_folder { ResultAction::Query(format!("{}{}{}", typed_dir, entry.name, separator)) }
+                        else { ResultAction::Open(full_path) },
+                }
+            }).collect();
+            if is_current() { sink.publish(&query, items, true); }
+        });
+    }
+}
+
+// The path query completed to the longest start the listed names share, a single folder with its separator
+pub fn completion(query: &str, entries: &[(String, bool)]) -> Option<String> {
+    let (typed_dir, partial) = split_query(query);
+    let partial_lower = partial.to_lowercase();
+    let matching = entries.iter()
+        .filter(|(name, _)| name.to_lowercase().starts_with(&partial_lower))
+        .collect::<Vec<_>>();
+    if let [(name, true)] = matching.as_slice() {
+        let separator = typed_dir.chars().last().unwrap_or('\\');
+        let completion = format!("{}{}{}", typed_dir, name, separator);
+        return if completion == query { None } else { Some(completion) };
+    }
+
+    let names = matching.into_iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+    history::complete_name(partial, &names).map(|name| typed_dir.to_string() + name.as_str())
+}
+
+fn is_separator(c: char) -> bool {
+    c == '\\' || c == '/'
+}
+
+// The folder part of a path query as typed and the start of a name after it, "C:\Users\ad" gives "C:\Users\" and "ad"
+fn split_query(query: &str) -> (&str, &str) {
+    let split = query.rfind(is_separator).map_or(0, |index| index + 1);
+    query.split_at(split)
+}
+
+// The folder a typed folder part stands for, "~" is the home folder
+fn expand(typed_dir: &str) -> String {
+    let dir = match typed_dir.strip_prefix('~') {
+        Some(rest) => env::var("USERPROFILE").unwrap_or_default() + rest,
+        None => typed_dir.to_string(),
+    };
+    dir.replace('/', "\\")
+}
+
+// The entries of a folder from the index, from the file system if no loaded volume has it.
+// A volume busy with an update is not waited for.
+fn list_folder(volumes: &[SharedVolume], dir: &str, is_current: &dyn Fn() -> bool) -> Option<Vec<FolderEntry>> {
+    let indexed = volumes.iter()
+        .filter(|shared| !shared.building.load(Ordering::Acquire))
+        .find_map(|shared| shared.volume.try_lock().ok()?.folder_entries(dir));
+    match indexed {
+        Some(entries) => Some(entries),
+        None => read_folder(dir, is_current),
+    }
+}
+
+// The entries of a folder on disk, None once the query is no longer current.
+// The folder attribute comes with each entry, no entry is opened to tell.
+fn read_folder(dir: &str, is_current: &dyn Fn() -> bool) -> Option<Vec<FolderEntry>> {
+    let Ok(read) = fs::read_dir(dir) else { return Some(Vec::new()); };
+    let mut entries = Vec::new();
+    for entry in read.flatten() {
+        if entries.len() % 1000 == 0 && !is_current() { return None; }
+        entries.push(FolderEntry {
+            name: entry.file_name().to_string_lossy().to_string(),
+            is_folder: entry.file_type().is_ok_and(|file_type| file_type.is_dir()),
+        });
+    }
+    Some(entries)
+}
@@ -0,0 +1,134 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::ui::SearchResultType;
+use crate::util::{file_util, log_util};
+use super::{ResultProvider, ResultSink, ResultItem, ResultAction};
+
+const EMOJI_PREFIX: &str = ":";
+const UNICODE_PREFIX: &str = "u:";
+const TABLE: &str = include_str!("../../../../assets/unicode.txt");
+const MAX_RESULTS: usize = 100;
+const MAX_RECENT: usize = 50;
+const BASE_SCORE: i32 = 3000; // the only results of these queries
+const RECENT_BONUS: i32 = 1000; // recently used characters first
+
+// A line of the table
+struct CharEntry {
+    character: &'static str,
+    emoji: bool,
+    name: &'static str, // lowercase
+    keywords: &'static str, // lowercase, space separated
+}
+
+// Emoji by ":smile" and all other characters of the table by "u:arrow", Enter copies the character
+pub struct UnicodeProvider {
+    table: Vec<CharEntry>,
+    recent: Vec<String>, // most recent first
+}
+
+impl UnicodeProvider {
+    pub fn new() -> UnicodeProvider {
+        let table = TABLE.lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                Some(CharEntry {
+                    character: fields.next()?,
+                    emoji: fields.next()? == "E",
+                    name: fields.next()?,
+                    keywords: fields.next().unwrap_or_default(),
+                })
+            })
+            .collect();
+        let recent = fs::read_to_string(Self::recent_path())
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        UnicodeProvider { table, recent }
+    }
+
+    fn recent_path() -> PathBuf {
+        file_util::get_userdata_path().join("unicode_recent.json")
+    }
+
+    fn save_recent(&self) -> Result<(), Box<dyn Error>> {
+        let root_path = file_util::get_userdata_path();
+        if !root_path.exists() { fs::create_dir_all(&root_path)?; }
+        fs::write(Self::recent_path(), serde_json::to_string(&self.recent)?)?;
+        Ok(())
+    }
+
+    // How well a character matches the words of a query, None if one of them is missing
+    fn score(entry: &CharEntry, words: &[String]) -> Option<i32> {
+        let mut score = 0;
+        for word in words {
+            let word_stem = stem(word);
+            if entry.keywords.split(' ').any(|keyword| keyword == word) { score += 40; continue; } // picked by hand
+            if entry.name.split(' ').any(|name_word| name_word == word) { score += 20; continue; }
+            let prefix = entry.name.split(' ').chain(entry.keywords.split(' ')).any(|name_word| stem(name_word).starts_with(word_stem));
+            if !prefix { return None; }
+            score += 10;
+        }
+        Some(score + (40 - entry.name.len() as i32 / 2).max(0)) // shorter names first
+    }
+}
+
+// The words after "u:" or ":" and whether only emoji are asked for, "u:\" is a path on drive U instead
+fn split_prefix(query: &str) -> Option<(&str, bool)> {
+    if let Some(filter) = query.strip_prefix(EMOJI_PREFIX) { return Some((filter, true)); }
+    let filter = query.strip_prefix(UNICODE_PREFIX)?;
+    if filter.starts_with(['\\', '/']) { None } else { Some((filter, false)) }
+}
+
+pub fn is_unicode_query(query: &str) -> bool {
+    split_prefix(query).is_some()
+}
+
+impl ResultProvider for UnicodeProvider {
+    fn query(&mut self, query: &str, sink: &ResultSink) {
+        let Some((filter, emoji_only)) = split_prefix(query) else { return; };
+        let words = filter.to_lowercase().split_whitespace().map(str::to_string).collect::<Vec<_>>();
+
+        let mut found = self.table.iter()
+            .filter(|entry| entry.emoji || !emoji_only)
+            .filter_map(|entry| {
+                let recent = self.recent.iter().position(|character| character == entry.character);
+                // without words only the recently used ones
+                let score = if words.is_empty() { recent.map(|_| 0)? } else { Self::score(entry, &words)? };
+                let bonus = recent.map(|position| RECENT_BONUS - position as i32).unwrap_or(0);
+                Some((BASE_SCORE + bonus + score, entry))
+            })
+            .collect::<Vec<_>>();
+        found.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+
+        let items = found.into_iter().take(MAX_RESULTS).map(|(score, entry)| ResultItem {
+            title: format!("{}   {}", entry.character, entry.name),
+            subtitle: entry.character.chars().map(|c| format!("U+{:04X}", c as u32)).collect::<Vec<_>>().join(" "),
+            icon_path: String::new(),
+            result_type: SearchResultType::System,
+            score,
+            action: ResultAction::Copy(entry.character.to_string()),
+        }).collect();
+        sink.publish(query, items, false);
+    }
+
+    fn activated(&mut self, item: &ResultItem) {
+        let ResultAction::Copy(character) = &item.action else { return; };
+        self.recent.retain(|c| c != character);
+        self.recent.insert(0, character.clone());
+        self.recent.truncate(MAX_RECENT);
+        self.save_recent().unwrap_or_else(|e| log_util::log_error(format!("Failed to save recent characters: {:?}", e)));
+    }
+}
+
+// Drop common english endings so that "smile" finds "smiling"
+fn stem(word: &str) -> &str {
+    for ending in ["ing", "ed", "es", "s", "e"] {
+        if let Some(stem) = word.strip_suffix(ending) {
+            if stem.len() >= 3 { return stem; }
+        }
+    }
+    word
+}
@@ -0,0 +1,192 @@
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, mpsc};
+use std::sync::atomic::Ordering;
+use std::thread;
+use serde::{Deserialize, Serialize};
+
+use crate::util::log_util;
+use super::file_data::SharedVolume;
+use super::volume::SearchResultItem;
+
+// Answers queries of other local tools over a localhost TCP socket, one JSON object per line:
+//   {"id": 1, "op": "query", "query": "foo", "offset": 0, "limit": 50, "sort": "rank"}
+//   {"id": 1, "op": "cancel"}
+// every query is answered with {"id", "results": [{"name", "path", "rank"}], "more"}
+// or {"id", "error"}, a cancelled query with {"id", "cancelled": true}.
+// The query must not be empty, limit is at most MAX_LIMIT and offset at most MAX_OFFSET.
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 1000;
+const MAX_OFFSET: usize = 10000;
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: u64,
+    op: String,
+    #[serde(default)]
+    query: String,
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+    #[serde(default)]
+    sort: String,
+}
+
+#[derive(Serialize)]
+struct ResultEntry {
+    name: String,
+    path: String,
+    rank: i8,
+}
+
+#[derive(Serialize)]
+struct Response {
+    id: u64,
+    results: Vec<ResultEntry>,
+    more: bool,
+}
+
+pub struct QueryServer {}
+
+impl QueryServer {
+    pub fn start(port: u16, volumes: Arc<Mutex<Vec<SharedVolume>>>) -> Result<(), Box<dyn Error>> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let volumes = volumes.clone();
+                        thread::spawn(move || {
+                            Self::serve(stream, volumes)
+                                .unwrap_or_else(|e| log_util::log_error(format!("QueryServer connection: {:?}", e)));
+                        });
+                    },
+                    Err(e) => log_util::log_error(format!("QueryServer accept: {:?}", e)),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    // Read requests until the client disconnects, a new query cancels the running one
+    fn serve(stream: TcpStream, volumes: Arc<Mutex<Vec<SharedVolume>>>) -> Result<(), Box<dyn Error>> {
+        let writer = Arc::new(Mutex::new(stream.try_clone()?));
+        let mut running: Option<mpsc::Sender<()>> = None;
+
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() { continue; }
+
+            let request = match serde_json::from_str::<Request>(&line) {
+                Ok(request) => request,
+                Err(e) => {
+                    Self::reply(&writer, serde_json::json!({ "id": 0, "error": format!("bad request: {}", e) }));
+                    continue;
+                }
+            };
+
+            if let Some(stop_sender) = running.take() { let _ = stop_sender.send(()); }
+            match request.op.as_str() {
+                "query" if request.query.trim().is_empty() => {
+                    Self::reply(&writer, serde_json::json!({ "id": request.id, "error": "empty query" }));
+                },
+                "query" if request.offset > MAX_OFFSET => {
+                    Self::reply(&writer, serde_json::json!({ "id": request.id, "error": format!("offset above {}", MAX_OFFSET) }));
+                },
+                "query" => {
+                    let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+                    running = Some(stop_sender);
+                    let (writer, volumes) = (writer.clone(), volumes.clone());
+                    thread::spawn(move || {
+                        let id = request.id;
+                        match Self::query(request, &volumes, &stop_receiver) {
+                            Some(response) => Self::reply(&writer, response),
+                            None => Self::reply(&writer, serde_json::json!({ "id": id, "cancelled": true })),
+                        }
+                    });
+                },
+                "cancel" => {}, // the running query was stopped above
+                op => Self::reply(&writer, serde_json::json!({ "id": request.id, "error": format!("unknown op: {}", op) })),
+            }
+        }
+
+        if let Some(stop_sender) = running { let _ = stop_sender.send(()); }
+        Ok(())
+    }
+
+    // Search every volume that is not being built, max results per volume or 0 for all, None if stopped.
+    // A volume is locked for one batch at a time, its index is released again if it was read only for this search
+    pub fn search_volumes(
+        volumes: &Mutex<Vec<SharedVolume>>,
+        query: &str,
+        max: usize,
+        stop_receiver: &mpsc::Receiver<()>
+    ) -> Option<Vec<SearchResultItem>> {
+        let volumes = volumes.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        let mut items: Vec<SearchResultItem> = Vec::new();
+        for SharedVolume { volume, building, .. } in volumes {
+            if building.load(Ordering::Acquire) { continue; }
+            let lock = || volume.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let mut found = Vec::new();
+            let mut after = None;
+            loop {
+                let Some((mut batch, next)) = lock().search_detached(query, after, stop_receiver) else {
+                    lock().end_borrow();
+                    return None;
+                };
+                found.append(&mut batch);
+                if next.is_none() || (max != 0 && found.len() >= max) { break; }
+                after = next;
+            }
+            lock().end_borrow();
+            if max != 0 { found.truncate(max); }
+            items.append(&mut found);
+        }
+        Some(items)
+    }
+
+    // None if the query was cancelled
+    fn query(request: Request, volumes: &Mutex<Vec<SharedVolume>>, stop_receiver: &mpsc::Receiver<()>) -> Option<Response> {
+        let limit = match request.limit {
+            Some(0) | None => DEFAULT_LIMIT,
+            Some(limit) => limit.min(MAX_LIMIT),
+        };
+        // ranked results only need the best of each volume, one more tells if there is a next page
+        let ranked = request.sort.is_empty() || request.sort == "rank";
+        let max = if ranked { request.offset + limit + 1 } else { 0 };
+
+        let mut items = Self::search_volumes(volumes, &request.query, max, stop_receiver)?;
+        match request.sort.as_str() {
+            "name" => items.sort_by_key(|item| item.file_name.to_lowercase()),
+            "path" => items.sort_by_key(|item| (item.path.to_lowercase(), item.file_name.to_lowercase())),
+            _ => items.sort_by_key(|item| std::cmp::Reverse(item.rank)), // sort by rank
+        }
+
+        let total = items.len();
+        let results = items.into_iter()
+            .skip(request.offset)
+            .take(limit)
+            .map(|item| ResultEntry {
+                path: item.path + item.file_name.as_str(),
+                name: item.file_name,
+                rank: item.rank,
+            })
+            .collect::<Vec<_>>();
+        let more = total > request.offset.saturating_add(results.len());
+        Some(Response { id: request.id, results, more })
+    }
+
+    fn reply(writer: &Mutex<TcpStream>, response: impl Serialize) {
+        let mut writer = writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match serde_json::to_string(&response) {
+            Ok(line) => {
+                writeln!(writer, "{}", line)
+                    .unwrap_or_else(|e| log_util::log_error(format!("QueryServer reply: {:?}", e)));
+            },
+            Err(e) => log_util::log_error(format!("QueryServer serialize: {:?}", e)),
+        }
+    }
+}
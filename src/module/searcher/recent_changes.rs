@@ -0,0 +1,179 @@
+use std::error::Error;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use slint::{ComponentHandle, VecModel};
+use i_slint_backend_winit::WinitWindowAccessor;
+
+use crate::core::application::app_config::AppConfig;
+use crate::ui::{RecentWindow, RecentRow_slint, RecentChange};
+use crate::util::{file_util, log_util};
+use super::volume::{ChangeLog, ChangeEvent, ChangeKind};
+
+const MAX_EVENTS: usize = 4096; // all of the log
+
+#[derive(Default)]
+struct RecentState {
+    events: Vec<ChangeEvent>, // newest first, read when the window is shown or refreshed
+    filter: String, // lowercase
+}
+
+// Lists the files created, modified, renamed and deleted lately, as recorded in the change log
+pub struct RecentChanges {
+    pub recent_win: RecentWindow,
+    state: Arc<Mutex<RecentState>>,
+}
+
+impl RecentChanges {
+    pub fn new() -> Result<RecentChanges, Box<dyn Error>> {
+        let recent_win = RecentWindow::new()?;
+        {
+            let mut app_config = AppConfig::global().lock()?;
+            recent_win.invoke_change_theme(app_config.get_theme() as i32);
+            app_config.recent_win = Some(recent_win.as_weak());
+        }
+
+        let state = Arc::new(Mutex::new(RecentState::default()));
+
+        { // filter change
+            let recent_win_clone = recent_win.as_weak();
+            let state = state.clone();
+            recent_win.on_filter_change(move |filter| {
+                let rows = {
+                    let mut state = state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    state.filter = filter.to_lowercase();
+                    Self::rows(&state)
+                };
+                if let Some(recent_win) = recent_win_clone.upgrade() {
+                    recent_win.set_rows(Rc::new(VecModel::from(rows)).into());
+                }
+            });
+        }
+
+        { // open, open dir
+            let state_clone = state.clone();
+            recent_win.on_open(move |id| {
+                let state = state_clone.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                if let Some(event) = state.events.get(id as usize) {
+                    file_util::open_file(event.path.clone())
+                        .unwrap_or_else(|e| log_util::log_error(format!("open_file error: {:?}", e)));
+                }
+            });
+
+            let state_clone = state.clone();
+            recent_win.on_open_dir(move |id| {
+                let state = state_clone.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                let Some(event) = state.events.get(id as usize) else { return; };
+                if let Some(parent) = Path::new(&event.path).parent() {
+                    file_util::open_file(parent.to_string_lossy().to_string())
+                        .unwrap_or_else(|e| log_util::log_error(format!("open_file error: {:?}", e)));
+                }
+            });
+        }
+
+        { // refresh
+            let recent_win_clone = recent_win.as_weak();
+            let state = state.clone();
+            recent_win.on_refresh(move || {
+                Self::load(&state, &recent_win_clone);
+            });
+        }
+
+        { // minimize, close, win move
+            let recent_win_clone = recent_win.as_weak();
+            recent_win.on_minimize(move || {
+                if let Some(recent_win) = recent_win_clone.upgrade() {
+                    recent_win.window().with_winit_window(|winit_win| {
+                        winit_win.set_minimized(true);
+                    });
+                }
+            });
+
+            let recent_win_clone = recent_win.as_weak();
+            recent_win.on_close(move || {
+                if let Some(recent_win) = recent_win_clone.upgrade() {
+                    let _ = recent_win.hide();
+                }
+            });
+
+            let recent_win_clone = recent_win.as_weak();
+            recent_win.on_win_move(move || {
+                if let Some(recent_win) = recent_win_clone.upgrade() {
+                    recent_win.window().with_winit_window(|winit_win| {
+                        winit_win.drag_window().unwrap_or_else(
+                            |e| log_util::log_error(format!("Failed to drag window: {:?}", e))
+                        );
+                    });
+                }
+            });
+        }
+
+        Ok(RecentChanges { recent_win, state })
+    }
+
+    pub fn show(&self) {
+        let _ = self.recent_win.show();
+        Self::load(&self.state, &self.recent_win.as_weak());
+    }
+
+    // Read the change log in the background, the volumes may be appending to it
+    fn load(state: &Arc<Mutex<RecentState>>, recent_win: &slint::Weak<RecentWindow>) {
+        if let Some(recent_win) = recent_win.upgrade() {
+            recent_win.set_loading(true);
+        }
+
+        let (state, recent_win) = (state.clone(), recent_win.clone());
+        thread::spawn(move || {
+            let events = ChangeLog::global()
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .recent(MAX_EVENTS)
+                .unwrap_or_else(|e| {
+                    log_util::log_error(format!("RecentChanges load error: {:?}", e));
+                    Vec::new()
+                });
+            let total = events.len();
+            let rows = {
+                let mut state = state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                state.events = events;
+                Self::rows(&state)
+            };
+
+            recent_win.upgrade_in_event_loop(move |recent_win| {
+                recent_win.set_rows(Rc::new(VecModel::from(rows)).into());
+                recent_win.set_total(total as i32);
+                recent_win.set_loading(false);
+            }).unwrap_or_else(|e| log_util::log_error(format!("RecentChanges publish: {:?}", e)));
+        });
+    }
+
+    // The events matching the filter, a row id is the position of its event
+    fn rows(state: &RecentState) -> Vec<RecentRow_slint> {
+        state.events.iter().enumerate()
+            .filter(|(_, event)| {
+                state.filter.is_empty()
+                    || event.path.to_lowercase().contains(&state.filter)
+                    || event.old_path.to_lowercase().contains(&state.filter)
+            })
+            .map(|(id, event)| RecentRow_slint {
+                id: id as i32,
+                time: format_time(event.time).into(),
+                change: match event.kind {
+                    ChangeKind::Created => RecentChange::Created,
+                    ChangeKind::Modified => RecentChange::Modified,
+                    ChangeKind::Renamed => RecentChange::Renamed,
+                    ChangeKind::Deleted => RecentChange::Deleted,
+                },
+                path: event.path.as_str().into(),
+                old_path: event.old_path.as_str().into(),
+            })
+            .collect()
+    }
+}
+
+fn format_time(time: i64) -> String {
+    chrono::DateTime::from_timestamp(time, 0)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
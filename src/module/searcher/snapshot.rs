@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
+use std::thread;
+use slint::{ComponentHandle, SharedString, VecModel};
+use i_slint_backend_winit::WinitWindowAccessor;
+
+use crate::core::application::app_config::AppConfig;
+use crate::ui::{SnapshotWindow, SnapshotDiffRow_slint, SnapshotChange, SnapshotStatus};
+use crate::util::{file_util, log_util};
+use super::exporter;
+use super::file_data::SharedVolume;
+use super::volume::{DiffEntry, Volume, VolumeKind};
+
+const MAX_SNAPSHOTS: usize = 20; // per volume, the oldest are deleted
+const MAX_SNAPSHOT_BYTES: u64 = 2 << 30; // of all snapshots together, the oldest are deleted beyond it
+const MAX_DIFF_ROWS: usize = 5000; // shown in the window, the export has all of them
+
+// A saved copy of the index of a volume, named <fd stem>@<time>.fd
+#[derive(Clone)]
+struct Snapshot {
+    stem: String,
+    time: String, // %Y%m%d-%H%M%S
+    path: PathBuf,
+}
+
+impl Snapshot {
+    fn dir() -> PathBuf {
+        file_util::get_userdata_path().join("snapshots")
+    }
+
+    // all snapshots, the newest first
+    fn list() -> Vec<Snapshot> {
+        let mut snapshots = fs::read_dir(Self::dir()).into_iter().flatten().flatten().filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("fd") { return None; }
+            let file_stem = path.file_stem()?.to_string_lossy().to_string();
+            let (stem, time) = file_stem.split_once('@')?;
+            Some(Snapshot { stem: stem.to_string(), time: time.to_string(), path })
+        }).collect::<Vec<_>>();
+        snapshots.sort_by(|a, b| b.time.cmp(&a.time).then_with(|| a.stem.cmp(&b.stem)));
+        snapshots
+    }
+
+    // the kind of the volume, snapshots of drives are named by their letter, the path of a root is not kept
+    fn kind(&self) -> VolumeKind {
+        match self.stem.chars().next() {
+            Some(drive) if self.stem.len() == 1 => VolumeKind::Ntfs(drive),
+            _ => VolumeKind::Folder(self.stem.clone()),
+        }
+    }
+
+    fn label(&self, names: &HashMap<String, String>) -> String {
+        let time = chrono::NaiveDateTime::parse_from_str(&self.time, "%Y%m%d-%H%M%S")
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or(self.time.clone());
+        format!("{}  {}", names.get(&self.stem).unwrap_or(&self.stem), time)
+    }
+}
+
+// Takes snapshots of the indexes and shows what changed between two of them
+pub struct SnapshotManager {
+    pub snapshot_win: SnapshotWindow,
+}
+
+impl SnapshotManager {
+    pub fn new(volumes: Arc<Mutex<Vec<SharedVolume>>>) -> Result<SnapshotManager, Box<dyn Error>> {
+        let snapshot_win = SnapshotWindow::new()?;
+        {
+            let mut app_config = AppConfig::global().lock()?;
+            snapshot_win.invoke_change_theme(app_config.get_theme() as i32);
+            app_config.snapshot_win = Some(snapshot_win.as_weak());
+        }
+
+        let snapshots = Arc::new(Mutex::new(Vec::new())); // in the order of the combo boxes
+        let diff = Arc::new(Mutex::new(Vec::<DiffEntry>::new())); // the last comparison, for the export
+        Self::publish_list(&snapshots, &snapshot_win.as_weak(), SnapshotStatus::Idle);
+
+        { // take snapshot
+            let snapshot_win_clone = snapshot_win.as_weak();
+            let snapshots = snapshots.clone();
+            snapshot_win.on_take_snapshot(move || {
+                if let Some(snapshot_win) = snapshot_win_clone.upgrade() {
+                    snapshot_win.set_status(SnapshotStatus::Taking);
+                }
+                let (volumes, snapshots, snapshot_win) = (volumes.clone(), snapshots.clone(), snapshot_win_clone.clone());
+                thread::spawn(move || {
+                    let status = match Self::take(&volumes) {
+                        Ok(_) => SnapshotStatus::Taken,
+                        Err(e) => {
+                            log_util::log_error(format!("Failed to take snapshot: {:?}", e));
+                            SnapshotStatus::Failed
+                        },
+                    };
+                    Self::publish_list(&snapshots, &snapshot_win, status);
+                });
+            });
+        }
+
+        { // compare
+            let snapshot_win_clone = snapshot_win.as_weak();
+            let (snapshots, diff) = (snapshots.clone(), diff.clone());
+            snapshot_win.on_compare(move |older, newer| {
+                let pair = {
+                    let snapshots = snapshots.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    snapshots.get(older as usize).cloned().zip(snapshots.get(newer as usize).cloned())
+                };
+                let Some((mut older, mut newer)) = pair else { return; };
+                let Some(snapshot_win) = snapshot_win_clone.upgrade() else { return; };
+                if older.stem != newer.stem {
+                    snapshot_win.set_status(SnapshotStatus::Mismatch);
+                    return;
+                }
+                if older.time > newer.time { std::mem::swap(&mut older, &mut newer); }
+                snapshot_win.set_status(SnapshotStatus::Comparing);
+
+                let (diff, snapshot_win) = (diff.clone(), snapshot_win_clone.clone());
+                thread::spawn(move || {
+                    match Volume::diff_snapshots(&older.kind(), &older.path.to_string_lossy(), &newer.path.to_string_lossy()) {
+                        Ok(entries) => Self::publish_diff(entries, &diff, &snapshot_win),
+                        Err(e) => {
+                            log_util::log_error(format!("Failed to compare snapshots: {:?}", e));
+                            snapshot_win.upgrade_in_event_loop(|snapshot_win| {
+                                snapshot_win.set_status(SnapshotStatus::Failed);
+                            }).unwrap_or_else(|e| log_util::log_error(format!("SnapshotManager publish status: {:?}", e)));
+                        },
+                    }
+                });
+            });
+        }
+
+        { // export diff
+            snapshot_win.on_export_diff(move |to_file| {
+                let diff = diff.clone();
+                thread::spawn(move || {
+                    let csv = Self::to_csv(&diff.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+                    exporter::write_text(csv, to_file, "Export snapshot diff", ("CSV Files", "*.csv"), "csv")
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to export snapshot diff: {:?}", e)));
+                });
+            });
+        }
+
+        { // minimize, close, win move
+            let snapshot_win_clone = snapshot_win.as_weak();
+            snapshot_win.on_minimize(move || {
+                if let Some(snapshot_win) = snapshot_win_clone.upgrade() {
+                    snapshot_win.window().with_winit_window(|winit_win| {
+                        winit_win.set_minimized(true);
+                    });
+                }
+            });
+
+            let snapshot_win_clone = snapshot_win.as_weak();
+            snapshot_win.on_close(move || {
+                if let Some(snapshot_win) = snapshot_win_clone.upgrade() {
+                    let _ = snapshot_win.hide();
+                }
+            });
+
+            let snapshot_win_clone = snapshot_win.as_weak();
+            snapshot_win.on_win_move(move || {
+                if let Some(snapshot_win) = snapshot_win_clone.upgrade() {
+                    snapshot_win.window().with_winit_window(|winit_win| {
+                        winit_win.drag_window().unwrap_or_else(
+                            |e| log_util::log_error(format!("Failed to drag window: {:?}", e))
+                        );
+                    });
+                }
+            });
+        }
+
+        Ok(SnapshotManager { snapshot_win })
+    }
+
+    // Snapshot every volume that is not being built, all with the same time
+    fn take(volumes: &Mutex<Vec<SharedVolume>>) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(Snapshot::dir())?;
+        let time = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let volumes = volumes.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        for SharedVolume { volume, building, .. } in volumes {
+            if building.load(Ordering::Acquire) { continue; }
+            let mut volume = volume.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let stem = Volume::fd_stem(&volume.kind);
+            let path = Snapshot::dir().join(format!("{}@{}.fd", stem, time));
+            volume.save_snapshot(&path.to_string_lossy())?;
+        }
+
+        // keep the newest snapshots of each volume within the size budget, the ones just taken in any case
+        let mut kept: HashMap<String, usize> = HashMap::new();
+        let mut total_size = 0;
+        for snapshot in Snapshot::list() {
+            let count = kept.entry(snapshot.stem.clone()).or_default();
+            *count += 1;
+            total_size += fs::metadata(&snapshot.path).map(|metadata| metadata.len()).unwrap_or(0);
+            if snapshot.time != time && (*count > MAX_SNAPSHOTS || total_size > MAX_SNAPSHOT_BYTES) {
+                fs::remove_file(&snapshot.path)
+                    .unwrap_or_else(|e| log_util::log_error(format!("Failed to remove snapshot {:?}: {:?}", snapshot.path, e)));
+            }
+        }
+        Ok(())
+    }
+
+    // readable names of the fd stems, the configured roots are shown by their path
+    fn names() -> HashMap<String, String> {
+        let roots = AppConfig::global()
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get_search_roots();
+        let mut names = roots.into_iter()
+            .map(|root| (Volume::fd_stem(&VolumeKind::Folder(root.clone())), root))
+            .collect::<HashMap<_, _>>();
+        for drive in 'A'..='Z' {
+            names.insert(Volume::fd_stem(&VolumeKind::Ntfs(drive)), format!("{}:", drive));
+        }
+        names
+    }
+
+    fn publish_list(snapshots: &Mutex<Vec<Snapshot>>, snapshot_win: &slint::Weak<SnapshotWindow>, status: SnapshotStatus) {
+        let list = Snapshot::list();
+        let names = Self::names();
+        let labels = list.iter().map(|snapshot| snapshot.label(&names)).collect::<Vec<_>>();
+        *snapshots.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = list;
+
+        snapshot_win.upgrade_in_event_loop(move |snapshot_win| {
+            let labels = labels.into_iter().map(SharedString::from).collect::<Vec<_>>();
+            snapshot_win.set_snapshots(Rc::new(VecModel::from(labels)).into());
+            snapshot_win.set_status(status);
+        }).unwrap_or_else(|e| log_util::log_error(format!("SnapshotManager publish list: {:?}", e)));
+    }
+
+    fn publish_diff(mut entries: Vec<DiffEntry>, diff: &Mutex<Vec<DiffEntry>>, snapshot_win: &slint::Weak<SnapshotWindow>) {
+        entries.sort_by(|a, b| Self::entry_path(a).cmp(Self::entry_path(b)));
+        let (mut added, mut removed, mut renamed) = (0, 0, 0);
+        for entry in &entries {
+            match entry {
+                DiffEntry::Added(_) => added += 1,
+                DiffEntry::Removed(_) => removed += 1,
+                DiffEntry::Renamed(_, _) => renamed += 1,
+            }
+        }
+        let rows = entries.iter().take(MAX_DIFF_ROWS).map(|entry| match entry {
+            DiffEntry::Added(path) => SnapshotDiffRow_slint { change: SnapshotChange::Added, path: path.as_str().into(), old_path: "".into() },
+            DiffEntry::Removed(path) => SnapshotDiffRow_slint { change: SnapshotChange::Removed, path: path.as_str().into(), old_path: "".into() },
+            DiffEntry::Renamed(old_path, path) => SnapshotDiffRow_slint { change: SnapshotChange::Renamed, path: path.as_str().into(), old_path: old_path.as_str().into() },
+        }).collect::<Vec<_>>();
+        *diff.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = entries;
+
+        snapshot_win.upgrade_in_event_loop(move |snapshot_win| {
+            snapshot_win.set_diff_rows(Rc::new(VecModel::from(rows)).into());
+            snapshot_win.set_added(added);
+            snapshot_win.set_removed(removed);
+            snapshot_win.set_renamed(renamed);
+            snapshot_win.set_has_diff(true);
+            snapshot_win.set_status(SnapshotStatus::Idle);
+        }).unwrap_or_else(|e| log_util::log_error(format!("SnapshotManager publish diff: {:?}", e)));
+    }
+
+    fn entry_path(entry: &DiffEntry) -> &str {
+        match entry {
+            DiffEntry::Added(path) | DiffEntry::Removed(path) | DiffEntry::Renamed(_, path) => path,
+        }
+    }
+
+    fn to_csv(entries: &[DiffEntry]) -> String {
+        let mut csv = String::from("change,path,old_path\r\n");
+        for entry in entries {
+            let (change, path, old_path) = match entry {
+                DiffEntry::Added(path) => ("added", path.as_str(), ""),
+                DiffEntry::Removed(path) => ("removed", path.as_str(), ""),
+                DiffEntry::Renamed(old_path, path) => ("renamed", path.as_str(), old_path.as_str()),
+            };
+            csv += &format!("{},{},{}\r\n", change, exporter::csv_field(path), exporter::csv_field(old_path));
+        }
+        csv
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::sync::{LazyLock, Mutex};
+
+use crate::util::{file_util, log_util};
+
+// The newest file changes of all volumes, kept in a ring of fixed size slots in changes.log:
+//   header: magic, next slot (u64), number of used slots (u64)
+//   slot: time (i64, unix seconds), kind (u8), path (u16 length + utf8), old path (u16 length + utf8)
+
+const MAGIC: &[u8; 4] = b"RCL1";
+const HEADER_SIZE: u64 = 20;
+const SLOT_SIZE: usize = 1024;
+const CAPACITY: u64 = 4096;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Created,
+    Modified, // the data was written, extended or truncated
+    Renamed,
+    Deleted,
+}
+
+#[derive(Clone)]
+pub struct ChangeEvent {
+    pub time: i64, // unix seconds
+    pub kind: ChangeKind,
+    pub path: String,
+    pub old_path: String, // the path before a rename, empty for other kinds
+}
+
+impl ChangeEvent {
+    fn encode(&self) -> Vec<u8> {
+        let mut slot = Vec::with_capacity(SLOT_SIZE);
+        slot.extend_from_slice(&self.time.to_be_bytes());
+        slot.push(self.kind as u8);
+        // both paths share the slot, long ones are cut
+        let max_len = (SLOT_SIZE - 13) / if self.old_path.is_empty() { 1 } else { 2 };
+        for path in [&self.path, &self.old_path] {
+            let path = truncate(path, max_len);
+            slot.extend_from_slice(&(path.len() as u16).to_be_bytes());
+            slot.extend_from_slice(path.as_bytes());
+        }
+        slot.resize(SLOT_SIZE, 0);
+        slot
+    }
+
+    fn decode(slot: &[u8]) -> Option<ChangeEvent> {
+        let time = i64::from_be_bytes(slot.get(0..8)?.try_into().ok()?);
+        let kind = match slot.get(8)? {
+            0 => ChangeKind::Created,
+            1 => ChangeKind::Modified,
+            2 => ChangeKind::Renamed,
+            3 => ChangeKind::Deleted,
+            _ => return None,
+        };
+        let mut offset = 9;
+        let mut paths = [String::new(), String::new()];
+        for path in &mut paths {
+            let len = u16::from_be_bytes(slot.get(offset..offset + 2)?.try_into().ok()?) as usize;
+            offset += 2;
+            *path = String::from_utf8_lossy(slot.get(offset..offset + len)?).to_string();
+            offset += len;
+        }
+        let [path, old_path] = paths;
+        Some(ChangeEvent { time, kind, path, old_path })
+    }
+}
+
+// Turns the records of one journal replay into events. The reasons of a record add up until the file
+// is closed, so a file shows up once per kind, and a rename is paired from its old and new name records.
+#[derive(Default)]
+pub struct JournalChanges {
+    events: VecDeque<ChangeEvent>, // the newest that fit into the log
+    seen: HashSet<(u64, u8)>, // file reference number and kind
+    old_paths: HashMap<u64, String>, // of renames waiting for the new name
+}
+
+impl JournalChanges {
+    pub fn created(&mut self, frn: u64, time: i64, path: Option<String>) {
+        self.push(frn, time, ChangeKind::Created, path, String::new());
+    }
+
+    pub fn modified(&mut self, frn: u64, time: i64, path: Option<String>) {
+        self.push(frn, time, ChangeKind::Modified, path, String::new());
+    }
+
+    pub fn deleted(&mut self, frn: u64, time: i64, path: Option<String>) {
+        self.old_paths.remove(&frn);
+        self.push(frn, time, ChangeKind::Deleted, path, String::new());
+    }
+
+    pub fn renamed_from(&mut self, frn: u64, path: Option<String>) {
+        if let Some(path) = path { self.old_paths.insert(frn, path); }
+    }
+
+    pub fn renamed_to(&mut self, frn: u64, time: i64, path: Option<String>) {
+        let Some(old_path) = self.old_paths.remove(&frn) else { return; };
+        let Some(path) = path else { return; };
+        self.add(ChangeEvent { time, kind: ChangeKind::Renamed, path, old_path });
+    }
+
+    fn push(&mut self, frn: u64, time: i64, kind: ChangeKind, path: Option<String>, old_path: String) {
+        let Some(path) = path else { return; };
+        if !self.seen.insert((frn, kind as u8)) { return; }
+        self.add(ChangeEvent { time, kind, path, old_path });
+    }
+
+    fn add(&mut self, event: ChangeEvent) {
+        if self.events.len() == CAPACITY as usize { self.events.pop_front(); }
+        self.events.push_back(event);
+    }
+
+    pub fn into_events(self) -> Vec<ChangeEvent> {
+        self.events.into()
+    }
+}
+
+// Seconds since the unix epoch of a FILETIME
+pub fn filetime_to_unix(filetime: i64) -> i64 {
+    (filetime - 116_444_736_000_000_000) / 10_000_000
+}
+
+// the longest prefix of text that fits into max_len bytes
+fn truncate(text: &str, max_len: usize) -> &str {
+    if text.len() <= max_len { return text; }
+    let mut end = max_len;
+    while !text.is_char_boundary(end) { end -= 1; }
+    &text[..end]
+}
+
+pub struct ChangeLog {
+    file: Option<File>, // None if changes.log can not be opened, changes are dropped then
+    next: u64,
+    count: u64,
+    pending: VecDeque<ChangeEvent>, // appended since the last take_pending
+    overflowed: bool, // pending events were dropped
+}
+
+impl ChangeLog {
+    fn new() -> ChangeLog {
+        match Self::open() {
+            Ok(change_log) => change_log,
+            Err(e) => {
+                log_util::log_error(format!("ChangeLog open error: {:?}", e));
+                ChangeLog { file: None, next: 0, count: 0, pending: VecDeque::new(), overflowed: false }
+            }
+        }
+    }
+
+    fn open() -> Result<ChangeLog, io::Error> {
+        let root_path = file_util::get_userdata_path();
+        if !root_path.exists() { fs::create_dir_all(&root_path)?; }
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(root_path.join("changes.log"))?;
+
+        let mut header = [0u8; HEADER_SIZE as usize];
+        if file.read_exact(&mut header).is_ok() && &header[0..4] == MAGIC {
+            let next = u64::from_be_bytes(header[4..12].try_into().unwrap_or_default());
+            let count = u64::from_be_bytes(header[12..20].try_into().unwrap_or_default());
+            if next < CAPACITY && count <= CAPACITY {
+                return Ok(ChangeLog { file: Some(file), next, count, pending: VecDeque::new(), overflowed: false });
+            }
+        }
+
+        // a new or broken log starts over
+        file.set_len(0)?;
+        let mut change_log = ChangeLog { file: Some(file), next: 0, count: 0, pending: VecDeque::new(), overflowed: false };
+        change_log.write_header()?;
+        Ok(change_log)
+    }
+
+    pub fn global() -> &'static Mutex<ChangeLog> {
+        &INSTANCE
+    }
+
+    fn write_header(&mut self) -> Result<(), io::Error> {
+        let Some(file) = &mut self.file else { return Ok(()); };
+        let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&self.next.to_be_bytes());
+        header.extend_from_slice(&self.count.to_be_bytes());
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&header)
+    }
+
+    // Add events, the oldest ones are overwritten when the ring is full
+    pub fn append(&mut self, events: &[ChangeEvent]) -> Result<(), io::Error> {
+        for event in events {
+            if self.pending.len() == CAPACITY as usize {
+                self.pending.pop_front();
+                self.overflowed = true;
+            }
+            self.pending.push_back(event.clone());
+        }
+
+        let Some(file) = &mut self.file else { return Ok(()); };
+        // only the newest events fit anyway
+        let skip = events.len().saturating_sub(CAPACITY as usize);
+        for event in &events[skip..] {
+            file.seek(SeekFrom::Start(HEADER_SIZE + self.next * SLOT_SIZE as u64))?;
+            file.write_all(&event.encode())?;
+            self.next = (self.next + 1) % CAPACITY;
+            self.count = (self.count + 1).min(CAPACITY);
+        }
+        self.write_header()
+    }
+
+    // The events appended since the last call, oldest first, None if some of them were dropped
+    pub fn take_pending(&mut self) -> Option<Vec<ChangeEvent>> {
+        let pending: Vec<ChangeEvent> = std::mem::take(&mut self.pending).into();
+        if std::mem::take(&mut self.overflowed) { return None; }
+        Some(pending)
+    }
+
+    // The newest events first, at most max
+    pub fn recent(&mut self, max: usize) -> Result<Vec<ChangeEvent>, io::Error> {
+        let (next, count) = (self.next, self.count);
+        let Some(file) = &mut self.file else { return Ok(Vec::new()); };
+        let mut events = Vec::new();
+        let mut slot = vec![0u8; SLOT_SIZE];
+        for i in 1..=count.min(max as u64) {
+            let position = (next + CAPACITY - i) % CAPACITY;
+            file.seek(SeekFrom::Start(HEADER_SIZE + position * SLOT_SIZE as u64))?;
+            file.read_exact(&mut slot)?;
+            events.extend(ChangeEvent::decode(&slot));
+        }
+        Ok(events)
+    }
+}
+
+static INSTANCE: LazyLock<Mutex<ChangeLog>> = LazyLock::new(|| {
+    Mutex::new(ChangeLog::new())
+});
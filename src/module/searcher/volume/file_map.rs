@@ -4,26 +4,69 @@ use std::io::{self, Write};
 use std::fs;
 use std::sync::mpsc::Receiver;
 
-use super::SearchResultItem;
 
+pub struct SearchResultItem {
+    pub path: String,
+    pub file_name: String,
+    pub rank: i8,
+}
+
+impl Clone for SearchResultItem {
+    fn clone(&self) -> Self {
+        SearchResultItem {
+            path: self.path.clone(),
+            file_name: self.file_name.clone(),
+            rank: self.rank,
+        }
+    }
+}
 
 pub struct File {
     pub parent_index: u64,
     pub file_name: String,
     pub filter: u32,
     pub rank: i8,
+    pub size: u64, // bytes of the data, 0 for folders
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
+// written before the entries of a .fd file, files of older formats are rebuilt
+const FD_MAGIC: &[u8; 4] = b"RFD2";
+const MAX_DEPTH: usize = 1024; // guards the walks up the parent chain against loops
+
+// one child of a folder in the disk usage view
+pub struct UsageEntry {
+    pub index: u64,
+    pub name: String,
+    pub size: u64, // with everything below it for folders
+    pub is_folder: bool,
+}
+
+// one child of a folder in the path listing of the search window
+pub struct FolderEntry {
+    pub name: String,
+    pub is_folder: bool, // has children in the index, empty folders are listed as files
+}
+
+// One difference between two snapshots of a volume, with full paths
+pub enum DiffEntry {
+    Added(String),
+    Removed(String),
+    Renamed(String, String), // old path, new path
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub struct FileKey {
     rank: i8,
     pub index: u64,
 }
 
+pub type UsageMap = HashMap<u64, u64, std::hash::BuildHasherDefault<fxhash::FxHasher>>; // folder index -> size of its tree
+
 pub struct FileMap {
     pub start_usn: i64,
     main_map: BTreeMap<FileKey, File>,
     rank_map: HashMap<u64, i8, std::hash::BuildHasherDefault<fxhash::FxHasher>>,
+    usage: Option<UsageMap>, // kept up to date once built
 }
 
 impl FileMap {
@@ -32,14 +75,32 @@ impl FileMap {
             start_usn: 0,
             main_map: BTreeMap::new(),
             rank_map: HashMap::default(),
+            usage: None,
         }
     }
 
-    // insert a file to the database by index, file name and parent index
-    pub fn insert(&mut self, index: u64, file_name: String, parent_index: u64) {
+    // insert a file to the database by index, file name, parent index and size
+    pub fn insert(&mut self, index: u64, file_name: String, parent_index: u64, size: u64) {
+        if self.rank_map.contains_key(&index) { self.remove(&index); } // the rank may change with the name
         let filter = make_filter(&file_name);
         let rank = Self::get_file_rank(&file_name);
-        self.insert_simple(index, File { parent_index, file_name, filter, rank });
+        self.insert_simple(index, File { parent_index, file_name, filter, rank, size });
+
+        if self.usage.is_some() {
+            // a folder that comes back, e.g. after a rename, brings the size of its tree
+            let total = self.usage.as_ref().and_then(|usage| usage.get(&index).copied()).unwrap_or(size);
+            self.add_usage(parent_index, total as i64);
+        }
+    }
+
+    // change the size of a file, e.g. when the journal reports that its data changed
+    pub fn set_size(&mut self, index: &u64, size: u64) {
+        let Some(rank) = self.rank_map.get(index) else { return; };
+        let file_key = FileKey { rank: *rank, index: *index };
+        let Some(file) = self.main_map.get_mut(&file_key) else { return; };
+        let (delta, parent_index) = (size as i64 - file.size as i64, file.parent_index);
+        file.size = size;
+        if delta != 0 { self.add_usage(parent_index, delta); }
     }
 
     // insert a file to the database by index and file struct
@@ -59,43 +120,368 @@ impl FileMap {
                 rank: self.rank_map[index],
                 index: *index,
             };
-            self.main_map.remove(&file_key);
+            if let Some(file) = self.main_map.remove(&file_key) {
+                if self.usage.is_some() {
+                    // the tree size of a folder is kept, its children stay in the map when it is renamed
+                    let total = self.usage.as_ref().and_then(|usage| usage.get(index).copied()).unwrap_or(file.size);
+                    self.add_usage(file.parent_index, -(total as i64));
+                }
+            }
             self.rank_map.remove(index);
         }
     }
 
-    // search for files by query
-    pub fn search(&self, query: &str, last_search_num: usize, batch: u8, stop_receiver: &Receiver<()> ) -> (Option<Vec<SearchResultItem>>, usize) {
+    pub fn has_usage(&self) -> bool {
+        self.usage.is_some()
+    }
+
+    // take the folder sizes out, to be put back once the same map is read again
+    pub fn take_usage(&mut self) -> Option<UsageMap> {
+        self.usage.take()
+    }
+
+    pub fn restore_usage(&mut self, usage: UsageMap) {
+        self.usage = Some(usage);
+    }
+
+    // Read the sizes of the files folder by folder, one listing of a folder gives the sizes of all its files.
+    // Entries of folders that can not be listed keep their size
+    pub fn fill_sizes(&mut self) {
+        let mut children: HashMap<u64, Vec<u64>> = HashMap::new();
+        for (file_key, file) in self.iter() {
+            children.entry(file.parent_index).or_default().push(file_key.index);
+        }
+        for (folder, indexes) in children {
+            let Some(path) = self.get_path(&folder) else { continue; };
+            let Ok(entries) = fs::read_dir(&path) else { continue; };
+            let sizes = entries.flatten()
+                .filter_map(|entry| {
+                    let metadata = entry.metadata().ok().filter(|metadata| metadata.is_file())?; // from the listing on windows
+                    Some((entry.file_name().to_string_lossy().to_string(), metadata.len()))
+                })
+                .collect::<HashMap<_, _>>();
+            for index in indexes {
+                let Some(rank) = self.rank_map.get(&index) else { continue; };
+                let Some(file) = self.main_map.get_mut(&FileKey { rank: *rank, index }) else { continue; };
+                if let Some(size) = sizes.get(&file.file_name) { file.size = *size; }
+            }
+        }
+    }
+
+    // Sum the sizes up the parent chain of every entry, later changes are applied as they come
+    pub fn build_usage(&mut self) {
+        if self.usage.is_some() { return; }
+        let mut usage: UsageMap = HashMap::default();
+        for file in self.main_map.values() {
+            let mut parent_index = file.parent_index;
+            for _ in 0..MAX_DEPTH {
+                if parent_index == 0 { break; }
+                *usage.entry(parent_index).or_default() += file.size;
+                match self.get(&parent_index) {
+                    Some(parent) => parent_index = parent.parent_index,
+                    None => break,
+                }
+            }
+        }
+        self.usage = Some(usage);
+    }
+
+    // The children of a folder with the size of their trees, the largest first
+    pub fn usage_children(&self, index: &u64) -> Vec<UsageEntry> {
+        let Some(usage) = &self.usage else { return Vec::new(); };
+        let mut children = self.iter()
+            .filter(|(_, file)| file.parent_index == *index)
+            .map(|(file_key, file)| {
+                let total = usage.get(&file_key.index).copied();
+                UsageEntry {
+                    index: file_key.index,
+                    name: file.file_name.clone(),
+                    size: total.unwrap_or(file.size),
+                    is_folder: total.is_some(),
+                }
+            })
+            .collect::<Vec<_>>();
+        children.sort_by_key(|child| std::cmp::Reverse(child.size));
+        children
+    }
+
+    // the size of the tree of a folder, None before build_usage
+    pub fn usage_of(&self, index: &u64) -> Option<u64> {
+        self.usage.as_ref()?.get(index).copied()
+    }
+
+    // The entry at the end of a chain of names below an entry, names are compared ignoring case
+    pub fn find_path(&self, root: &u64, names: &[&str]) -> Option<u64> {
+        let Some((last, parents)) = names.split_last() else { return Some(*root); };
+        self.iter()
+            .filter(|(_, file)| same_name(&file.file_name, last))
+            .find(|(_, file)| {
+                let mut parent_index = file.parent_index;
+                for name in parents.iter().rev() {
+                    match self.get(&parent_index) {
+                        Some(parent) if same_name(&parent.file_name, name) => parent_index = parent.parent_index,
+                        _ => return false,
+                    }
+                }
+                parent_index == *root
+            })
+            .map(|(file_key, _)| file_key.index)
+    }
+
+    // The children of a folder, an entry is a folder if another entry names it as parent
+    pub fn folder_entries(&self, index: &u64) -> Vec<FolderEntry> {
+        let children = self.iter()
+            .filter(|(_, file)| file.parent_index == *index)
+            .map(|(file_key, _)| file_key.index)
+            .collect::<std::collections::HashSet<_>>();
+        let folders = self.main_map.values()
+            .filter(|file| children.contains(&file.parent_index))
+            .map(|file| file.parent_index)
+            .collect::<std::collections::HashSet<_>>();
+        children.iter()
+            .filter_map(|child| Some(FolderEntry { name: self.get(child)?.file_name.clone(), is_folder: folders.contains(child) }))
+            .collect()
+    }
+
+    // add delta to the tree size of parent_index and of all folders above it
+    fn add_usage(&mut self, mut parent_index: u64, delta: i64) {
+        let Some(mut usage) = self.usage.take() else { return; };
+        for _ in 0..MAX_DEPTH {
+            if parent_index == 0 { break; }
+            let total = usage.entry(parent_index).or_default();
+            *total = (*total as i64).saturating_add(delta).max(0) as u64;
+            match self.get(&parent_index) {
+                Some(parent) => parent_index = parent.parent_index,
+                None => break,
+            }
+        }
+        self.usage = Some(usage);
+    }
+
+    // search for files by query, the indexes of the found files are appended to matched
+    pub fn search(&self, query: &str, last_search_num: usize, batch: u8, matched: &mut Vec<u64>, stop_receiver: &Receiver<()> ) -> (Option<Vec<SearchResultItem>>, usize) {
         let mut result = Vec::new();
-        let mut find_num = 0;
+        let mut found = Vec::new();
         let mut search_num: usize = 0;
         let query_lower = query.to_lowercase();
         let query_filter = make_filter(&query_lower);
 
         let file_map_iter = self.iter().rev().skip(last_search_num);
-        for (_, file) in file_map_iter {
+        for (file_key, file) in file_map_iter {
             if stop_receiver.try_recv().is_ok() { return (None, 0); }
             search_num += 1;
-            if (file.filter & query_filter) == query_filter && match_str(&file.file_name, &query_lower) {
-                if let Some(path) = self.get_path(&file.parent_index) {
-                    result.push(SearchResultItem {
-                        path,
-                        file_name: file.file_name.clone(),
-                        rank: file.rank,
-                    });
-                    find_num += 1;
-                    if find_num >= batch { break; }
-                }
+            if let Some(item) = self.match_file(file, &query_lower, query_filter) {
+                result.push(item);
+                found.push(file_key.index);
+                if result.len() >= batch as usize { break; }
             }
         }
 
+        matched.append(&mut found);
         (Some(result), search_num)
     }
 
+    // search only in the given files, used to narrow down the matches of a broader query
+    pub fn search_in(&self, query: &str, candidates: &[u64], batch: u8, matched: &mut Vec<u64>, stop_receiver: &Receiver<()> ) -> (Option<Vec<SearchResultItem>>, usize) {
+        let mut result = Vec::new();
+        let mut found = Vec::new();
+        let mut search_num: usize = 0;
+        let query_lower = query.to_lowercase();
+        let query_filter = make_filter(&query_lower);
+
+        for index in candidates {
+            if stop_receiver.try_recv().is_ok() { return (None, 0); }
+            search_num += 1;
+            if let Some(item) = self.get(index).and_then(|file| self.match_file(file, &query_lower, query_filter)) {
+                result.push(item);
+                found.push(*index);
+                if result.len() >= batch as usize { break; }
+            }
+        }
+
+        matched.append(&mut found);
+        (Some(result), search_num)
+    }
+
+    // the best max matches in rank order, 0 for all of them, None if stopped
+    pub fn search_top(&self, query: &str, max: usize, stop_receiver: &Receiver<()>) -> Option<Vec<SearchResultItem>> {
+        let mut result = Vec::new();
+        let query_lower = query.to_lowercase();
+        let query_filter = make_filter(&query_lower);
+
+        // one pass, the map is ordered by rank already
+        for (_, file) in self.iter().rev() {
+            if stop_receiver.try_recv().is_ok() { return None; }
+            if let Some(item) = self.match_file(file, &query_lower, query_filter) {
+                result.push(item);
+                if result.len() == max { break; }
+            }
+        }
+        Some(result)
+    }
+
+    // the matches among the next scan entries in rank order, continuing below the key of the last batch, None to start.
+    // Return them and the key to continue after, None once the map is exhausted, or None if stopped
+    pub fn search_from(&self, query: &str, after: Option<FileKey>, scan: usize, stop_receiver: &Receiver<()>)
+        -> Option<(Vec<SearchResultItem>, Option<FileKey>)> {
+        let mut result = Vec::new();
+        let query_lower = query.to_lowercase();
+        let query_filter = make_filter(&query_lower);
+
+        let entries = match after {
+            Some(key) => self.main_map.range(..key),
+            None => self.main_map.range(..),
+        };
+        let mut last = None;
+        for (file_key, file) in entries.rev().take(scan) {
+            if stop_receiver.try_recv().is_ok() { return None; }
+            if let Some(item) = self.match_file(file, &query_lower, query_filter) {
+                result.push(item);
+            }
+            last = Some(*file_key);
+        }
+        let next = last.filter(|key| self.main_map.range(..*key).next_back().is_some());
+        Some((result, next))
+    }
+
+    // the result items of files found before, skipping the ones removed meanwhile
+    pub fn get_items(&self, indexes: &[u64]) -> Vec<SearchResultItem> {
+        indexes.iter().filter_map(|index| {
+            let file = self.get(index)?;
+            let path = self.get_path(&file.parent_index)?;
+            Some(SearchResultItem { path, file_name: file.file_name.clone(), rank: file.rank })
+        }).collect()
+    }
+
+    // files grouped by lowercased name, entries that have children are folders and left out
+    pub fn name_groups(&self) -> HashMap<String, Vec<u64>> {
+        let folders = self.main_map.values().map(|file| file.parent_index).collect::<std::collections::HashSet<_>>();
+        let mut groups: HashMap<String, Vec<u64>> = HashMap::new();
+        for (file_key, file) in self.iter() {
+            if folders.contains(&file_key.index) { continue; }
+            groups.entry(file.file_name.to_lowercase()).or_default().push(file_key.index);
+        }
+        groups
+    }
+
+    // full paths of the files with one of the given lowercased extensions
+    pub fn paths_with_extensions(&self, extensions: &[&str]) -> Vec<String> {
+        self.iter()
+            .filter(|(_, file)| {
+                file.file_name.rsplit_once('.')
+                    .is_some_and(|(_, extension)| extensions.contains(&extension.to_lowercase().as_str()))
+            })
+            .filter_map(|(file_key, _)| self.get_full_path(&file_key.index))
+            .collect()
+    }
+
+    // the most common extensions and their number of files, lowercased and without the dot
+    pub fn top_extensions(&self, max: usize) -> Vec<(String, usize)> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for file in self.main_map.values() {
+            if let Some((stem, extension)) = file.file_name.rsplit_once('.') {
+                if stem.is_empty() || extension.is_empty() { continue; } // .gitignore and the like
+                *counts.entry(extension.to_lowercase()).or_default() += 1;
+            }
+        }
+        let mut counts = counts.into_iter().collect::<Vec<_>>();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts.truncate(max);
+        counts
+    }
+
+    pub fn get_full_path(&self, index: &u64) -> Option<String> {
+        let file = self.get(index)?;
+        Some(self.get_path(&file.parent_index)? + file.file_name.as_str())
+    }
+
+    // What changed from this map to a newer one of the same volume, entries are matched by index
+    pub fn diff(&self, newer: &FileMap) -> Vec<DiffEntry> {
+        let mut entries = Vec::new();
+        for (file_key, file) in newer.iter() {
+            match self.get(&file_key.index) {
+                None => entries.extend(newer.get_full_path(&file_key.index).map(DiffEntry::Added)),
+                Some(old) if old.file_name != file.file_name || old.parent_index != file.parent_index => {
+                    if let (Some(old_path), Some(new_path)) = (self.get_full_path(&file_key.index), newer.get_full_path(&file_key.index)) {
+                        entries.push(DiffEntry::Renamed(old_path, new_path));
+                    }
+                },
+                Some(_) => {},
+            }
+        }
+        for (file_key, _) in self.iter() {
+            if newer.get(&file_key.index).is_none() {
+                entries.extend(self.get_full_path(&file_key.index).map(DiffEntry::Removed));
+            }
+        }
+        entries
+    }
+
+    // The same by full path, for maps whose indexes differ between builds. A removed and an added path
+    // are taken for a rename when their name is unique among both (a move), or else when they are
+    // the only ones left in their folder (a rename in place)
+    pub fn diff_by_path(&self, newer: &FileMap) -> Vec<DiffEntry> {
+        let old_paths = self.full_path_set();
+        let new_paths = newer.full_path_set();
+        let mut added = new_paths.difference(&old_paths).cloned().collect::<Vec<_>>();
+        let mut removed = old_paths.difference(&new_paths).cloned().collect::<Vec<_>>();
+        let mut entries = Vec::new();
+        for key in [Self::path_name as fn(&str) -> &str, Self::path_parent] {
+            let renamed = {
+                let added_keys = Self::unique_keys(&added, key);
+                Self::unique_keys(&removed, key).into_iter()
+                    .filter_map(|(key, old_path)| Some((old_path.to_string(), added_keys.get(key)?.to_string())))
+                    .collect::<HashMap<_, _>>()
+            };
+            let new_paths = renamed.values().cloned().collect::<std::collections::HashSet<_>>();
+            removed.retain(|old_path| !renamed.contains_key(old_path));
+            added.retain(|new_path| !new_paths.contains(new_path));
+            entries.extend(renamed.into_iter().map(|(old_path, new_path)| DiffEntry::Renamed(old_path, new_path)));
+        }
+        entries.extend(added.into_iter().map(DiffEntry::Added));
+        entries.extend(removed.into_iter().map(DiffEntry::Removed));
+        entries
+    }
+
+    // the paths by key, for keys only one of the paths has
+    fn unique_keys<'a>(paths: &'a [String], key: fn(&str) -> &str) -> HashMap<&'a str, &'a str> {
+        let mut keys: HashMap<&str, Option<&str>> = HashMap::new();
+        for path in paths {
+            keys.entry(key(path)).and_modify(|unique| *unique = None).or_insert(Some(path));
+        }
+        keys.into_iter().filter_map(|(key, path)| Some((key, path?))).collect()
+    }
+
+    fn path_name(path: &str) -> &str {
+        path.rsplit_once('\\').map_or(path, |(_, name)| name)
+    }
+
+    fn path_parent(path: &str) -> &str {
+        path.rsplit_once('\\').map_or("", |(parent, _)| parent)
+    }
+
+    fn full_path_set(&self) -> std::collections::HashSet<String> {
+        self.iter().filter_map(|(file_key, _)| self.get_full_path(&file_key.index)).collect()
+    }
+
+    fn match_file(&self, file: &File, query_lower: &str, query_filter: u32) -> Option<SearchResultItem> {
+        if (file.filter & query_filter) == query_filter && match_str(&file.file_name, query_lower) {
+            if let Some(path) = self.get_path(&file.parent_index) {
+                return Some(SearchResultItem {
+                    path,
+                    file_name: file.file_name.clone(),
+                    rank: file.rank,
+                });
+            }
+        }
+        None
+    }
+
     pub fn save(&self, path: &str) -> Result<(), std::io::Error> {
         let mut save_file = fs::File::create(path)?;
 
         let mut buf = Vec::new();
+        buf.write_all(FD_MAGIC)?;
         buf.write_all(&self.start_usn.to_be_bytes())?;
         for (file_key, file) in self.iter() {
             buf.write_all(&file_key.index.to_be_bytes())?;
@@ -104,6 +490,7 @@ impl FileMap {
             buf.write_all(file.file_name.as_bytes())?;
             buf.write_all(&file.filter.to_be_bytes())?;
             buf.write_all(&file.rank.to_be_bytes())?;
+            buf.write_all(&file.size.to_be_bytes())?;
         }
         save_file.write_all(&buf.to_vec())?;
 
@@ -113,10 +500,11 @@ impl FileMap {
     pub fn read(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
         let file_data = fs::read(path)?;
 
-        if file_data.len() < 8 { return Err(io::Error::new(io::ErrorKind::InvalidData, "File data too short.").into()); }
+        if file_data.len() < 12 { return Err(io::Error::new(io::ErrorKind::InvalidData, "File data too short.").into()); }
+        if &file_data[0..4] != FD_MAGIC { return Err(io::Error::new(io::ErrorKind::InvalidData, "Old file format.").into()); }
 
-        self.start_usn = i64::from_be_bytes(file_data[0..8].try_into()?);
-        let mut ptr_index = 8;
+        self.start_usn = i64::from_be_bytes(file_data[4..12].try_into()?);
+        let mut ptr_index = 12;
 
         while ptr_index < file_data.len() {
             if ptr_index + 18 > file_data.len() { return Err(io::Error::new(io::ErrorKind::InvalidData, "File data size error.").into()); }
@@ -128,7 +516,7 @@ impl FileMap {
             let file_name_len = u16::from_be_bytes(file_data[ptr_index..ptr_index+2].try_into()?) as u16;
             ptr_index += 2;
 
-            if ptr_index + (file_name_len as usize) + 5 > file_data.len() { return Err(io::Error::new(io::ErrorKind::InvalidData, "File data size error.").into()); }
+            if ptr_index + (file_name_len as usize) + 13 > file_data.len() { return Err(io::Error::new(io::ErrorKind::InvalidData, "File data size error.").into()); }
 
             let file_name = String::from_utf8(file_data[ptr_index..(ptr_index + file_name_len as usize)].to_vec())?;
             ptr_index += file_name_len as usize;
@@ -136,7 +524,9 @@ impl FileMap {
             ptr_index += 4;
             let rank = i8::from_be_bytes(file_data[ptr_index..ptr_index+1].try_into()?);
             ptr_index += 1;
-            self.insert_simple(index, File { parent_index, file_name, filter, rank });
+            let size = u64::from_be_bytes(file_data[ptr_index..ptr_index+8].try_into()?);
+            ptr_index += 8;
+            self.insert_simple(index, File { parent_index, file_name, filter, rank, size });
         }
 
         Ok(())
@@ -145,12 +535,26 @@ impl FileMap {
     pub fn clear(&mut self) {
         self.main_map.clear();
         self.rank_map.clear();
+        self.usage = None;
     }
 
     pub fn is_empty(&self) -> bool {
         self.main_map.is_empty()
     }
 
+    pub fn len(&self) -> usize {
+        self.main_map.len()
+    }
+
+    // rough number of bytes held by the maps, used for the memory budget of the searcher
+    pub fn memory_size(&self) -> usize {
+        let entry_size = std::mem::size_of::<FileKey>() + std::mem::size_of::<File>() // main_map entry
+            + std::mem::size_of::<u64>() + std::mem::size_of::<i8>() // rank_map entry
+            + 16; // tree node and hash table overhead
+        let usage_size = self.usage.as_ref().map(|usage| usage.len() * (2 * std::mem::size_of::<u64>() + 8)).unwrap_or(0);
+        self.main_map.values().map(|file| entry_size + file.file_name.capacity()).sum::<usize>() + usage_size
+    }
+
     // get a File by index
     fn get(&self, index: &u64) -> Option<&File> {
         if let Some(rank) = self.rank_map.get(index) {
@@ -229,6 +633,18 @@ fn make_filter(str: &str) -> u32 {
     address
 }
 
+// The rank of a name if it matches the query like an entry of the index would, for names found elsewhere
+pub fn match_name(name: &str, query_lower: &str) -> Option<i8> {
+    let query_filter = make_filter(query_lower);
+    if (make_filter(name) & query_filter) != query_filter || !match_str(name, query_lower) { return None; }
+    Some(FileMap::get_file_rank(name))
+}
+
+// Whether two file names are the same for windows
+fn same_name(name: &str, other: &str) -> bool {
+    name.eq_ignore_ascii_case(other) || (!name.is_ascii() && name.to_lowercase() == other.to_lowercase())
+}
+
 // return true if contain query
 fn match_str(contain: &str, query_lower: &str) -> bool {
     let mut lower_contain = contain.to_lowercase();
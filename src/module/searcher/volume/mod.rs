@@ -1,61 +1,266 @@
 mod file_map;
+mod walker;
+mod change_log;
 
 use std::{fs, io};
-use std::sync::mpsc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{mpsc, Arc, Mutex};
 use std::error::Error;
 use std::ffi::{c_void, CString};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use windows::Win32::Foundation;
 use windows::Win32::Foundation::HANDLE;
-use windows::Win32::System::{IO, Ioctl};
+use windows::Win32::System::{IO, Ioctl, Threading};
 use windows::Win32::Storage::FileSystem;
-#[allow(unused_imports)]
-use std::time::SystemTime;
 
 #[allow(unused_imports)]
 use crate::util::log_util::{log_error, log_info};
 use crate::util::file_util;
+use crate::core::application::app_config::AppConfig;
+use crate::ui::IndexPhase;
 use file_map::FileMap;
+use file_map::UsageMap;
+pub use file_map::{SearchResultItem, DiffEntry, UsageEntry, FolderEntry, FileKey, match_name};
+pub use change_log::{ChangeLog, ChangeEvent, ChangeKind};
+use change_log::JournalChanges;
+
+
+// What a volume is busy with, read by the searcher to show indexing progress
+pub struct IndexProgress {
+    pub name: String,
+    pub phase: IndexPhase,
+    pub records: usize,
+    pub started: Instant,
+}
 
-
-pub struct SearchResultItem {
-    pub path: String,
-    pub file_name: String,
-    pub rank: i8,
+// Where the search of a query stopped, kept to continue paging, to narrow refined queries
+// and to answer recent queries again without scanning
+#[derive(Default)]
+struct QueryState {
+    query: String,
+    matched: Vec<u64>, // files found so far, in scan order
+    sent: usize, // matches handed out to the search window, the rest go out before the search continues
+    candidates: Vec<u64>, // matches of a broader query, checked before the scan continues
+    next_candidate: usize,
+    scanned: usize, // entries of the file map walked through
 }
 
-impl Clone for SearchResultItem {
-    fn clone(&self) -> Self {
-        SearchResultItem {
-            path: self.path.clone(),
-            file_name: self.file_name.clone(),
-            rank: self.rank,
-        }
+impl QueryState {
+    fn new(query: String) -> QueryState {
+        QueryState { query, ..Default::default() }
+    }
+
+    // Start from the matches of a broader query, the entries it did not reach are scanned as usual
+    fn narrow(query: String, broader: &QueryState) -> QueryState {
+        let mut candidates = broader.matched.clone();
+        candidates.extend_from_slice(&broader.candidates[broader.next_candidate..]);
+        QueryState { query, candidates, scanned: broader.scanned, ..Default::default() }
+    }
+
+    // true if every file matching query also matches the broader one
+    fn refines(query: &str, broader: &str) -> bool {
+        if broader.is_empty() || query == broader { return false; }
+        let (query, broader) = (query.to_lowercase(), broader.to_lowercase());
+        query.starts_with(&broader) || (!broader.contains('*') && query.contains(&broader))
     }
 }
 
+const QUERY_CACHE_SIZE: usize = 8;
+const TOP_EXTENSIONS: usize = 10;
+const DETACHED_BATCH: usize = 100_000; // entries searched per lock by search_detached
+
+// How long the index operations of a volume took the last time they ran
+#[derive(Default, Clone)]
+pub struct IndexMetrics {
+    pub build: Option<Duration>,
+    pub update: Option<Duration>,
+    pub update_records: usize, // journal records applied by the last update
+    pub read: Option<Duration>,
+    pub write: Option<Duration>,
+    pub find: Option<Duration>,
+}
+
+// A snapshot of a volume for the diagnostics view
+pub struct VolumeDiagnostics {
+    pub name: String,
+    pub loaded: bool,
+    pub entry_count: usize,
+    pub fd_size: Option<u64>,
+    pub memory_size: usize,
+    pub last_build: Option<SystemTime>,
+    pub metrics: IndexMetrics,
+    pub start_usn: i64,
+    pub journal_id: Option<u64>, // None for folder roots and drives whose journal can not be queried
+    pub top_extensions: Vec<(String, usize)>,
+}
+
+// The backend used to fill the file map of a volume
+#[derive(Clone, PartialEq)]
+pub enum VolumeKind {
+    Ntfs(char), // a ntfs drive, read from the MFT and kept fresh by the USN journal
+    Folder(String), // a user configured root, read by walking the directory tree
+}
+
 pub struct Volume {
-    pub drive: char,
+    pub kind: VolumeKind,
+    pub name: String,
     drive_frn: u64,
     ujd: Ioctl::USN_JOURNAL_DATA_V0,
     file_map: FileMap,
     stop_receiver: mpsc::Receiver<()>,
-    last_query: String,
-    last_search_num: usize,
+    query_state: QueryState,
+    query_cache: VecDeque<QueryState>, // recent queries, the most recent first
+    entry_count: usize,
+    watch_handle: isize, // change notification handle of a folder root, 0 if not watched
+    last_build: Option<SystemTime>,
+    progress: Arc<Mutex<IndexProgress>>,
+    hits: usize, // number of queries this volume returned results for
+    metrics: IndexMetrics,
+    // the numbers of the diagnostics view, recorded when the file map was last built or read
+    top_extensions: Vec<(String, usize)>,
+    memory_size: usize,
+    start_usn: i64,
+    borrowed: bool, // read by a caller outside the search window, released again by end_borrow
+    sizes_read: bool, // the file sizes of a drive were read for the disk usage view and saved with the index
+    kept_usage: Option<UsageMap>, // the folder sizes of the released file map, put back when it is read again
 }
 
 impl Volume {
-    pub fn new(drive: char, stop_receiver: mpsc::Receiver<()>) -> Volume {
+    pub fn new(kind: VolumeKind, stop_receiver: mpsc::Receiver<()>) -> Volume {
+        let name = match &kind {
+            VolumeKind::Ntfs(drive) => drive.to_string(),
+            VolumeKind::Folder(root) => root.clone(),
+        };
+        let progress = IndexProgress {
+            name: match &kind {
+                VolumeKind::Ntfs(drive) => format!("{}:", drive),
+                VolumeKind::Folder(root) => root.clone(),
+            },
+            phase: IndexPhase::Idle,
+            records: 0,
+            started: Instant::now(),
+        };
         Volume {
-            drive,
+            kind,
+            name,
             drive_frn: 0x5000000000005,
             file_map: FileMap::new(),
             ujd: Ioctl::USN_JOURNAL_DATA_V0{ UsnJournalID: 0x0, FirstUsn: 0x0, NextUsn: 0x0, LowestValidUsn: 0x0, MaxUsn: 0x0, MaximumSize: 0x0, AllocationDelta: 0x0 },
             stop_receiver,
-            last_query: String::new(),
-            last_search_num: 0,
+            query_state: QueryState::default(),
+            query_cache: VecDeque::new(),
+            entry_count: 0,
+            watch_handle: 0,
+            last_build: None,
+            progress: Arc::new(Mutex::new(progress)),
+            hits: 0,
+            metrics: IndexMetrics::default(),
+            top_extensions: Vec::new(),
+            memory_size: 0,
+            start_usn: 0,
+            borrowed: false,
+            sizes_read: false,
+            kept_usage: None,
+        }
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        !self.file_map.is_empty()
+    }
+
+    // the memory the loaded file map takes, as recorded when it was built or read
+    pub fn memory_size(&self) -> usize {
+        if self.is_loaded() { self.memory_size } else { 0 }
+    }
+
+    // record the numbers of the diagnostics view, counting them takes a pass over the file map
+    fn record_stats(&mut self) {
+        self.top_extensions = self.file_map.top_extensions(TOP_EXTENSIONS);
+        self.memory_size = self.file_map.memory_size();
+        self.start_usn = self.file_map.start_usn;
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    pub fn progress(&self) -> Arc<Mutex<IndexProgress>> {
+        self.progress.clone()
+    }
+
+    fn set_phase(&self, phase: IndexPhase) {
+        let mut progress = self.progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        progress.phase = phase;
+        progress.records = 0;
+        progress.started = Instant::now();
+    }
+
+    fn add_records(&self, records: usize) {
+        self.progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).records += records;
+    }
+
+    // number of entries of the last build or read, 0 if the volume was never indexed
+    pub fn entry_count(&self) -> usize {
+        self.entry_count
+    }
+
+    // Collect the numbers of the diagnostics view from the ones recorded, the file map is not loaded or counted for it
+    pub fn diagnostics(&self) -> VolumeDiagnostics {
+        let fd_metadata = fs::metadata(Self::fd_path(&self.kind)).ok();
+        let journal_id = match self.kind {
+            VolumeKind::Ntfs(drive) => Self::query_journal(drive).map(|ujd| ujd.UsnJournalID),
+            VolumeKind::Folder(_) => None,
+        };
+        VolumeDiagnostics {
+            name: self.progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).name.clone(),
+            loaded: self.is_loaded(),
+            entry_count: self.entry_count,
+            fd_size: fd_metadata.as_ref().map(|metadata| metadata.len()),
+            memory_size: self.memory_size(),
+            // the .fd file is only written by a full build
+            last_build: self.last_build.or_else(|| fd_metadata.and_then(|metadata| metadata.modified().ok())),
+            metrics: self.metrics.clone(),
+            start_usn: if self.is_loaded() { self.file_map.start_usn } else { self.start_usn }, // the journal moves it on
+            journal_id,
+            top_extensions: self.top_extensions.clone(),
+        }
+    }
+
+    // the name of the .fd file a volume of this kind is serialized to, without extension
+    pub fn fd_stem(kind: &VolumeKind) -> String {
+        match kind {
+            VolumeKind::Ntfs(drive) => drive.to_string(),
+            VolumeKind::Folder(root) => format!("root_{:016x}", fxhash::hash64(root)),
         }
     }
 
+    // the path of the .fd file a volume of this kind is serialized to
+    pub fn fd_path(kind: &VolumeKind) -> String {
+        let file_path = file_util::get_userdata_path();
+        format!("{}/{}.fd", file_path.to_str().unwrap_or("."), Self::fd_stem(kind))
+    }
+
+    // Save a copy of the index. A loaded file map is saved as the search window has it, updating it would
+    // restart a running search. Otherwise it is read, brought up to date and released again
+    pub fn save_snapshot(&mut self, path: &str) -> Result<(), io::Error> {
+        if self.is_loaded() { return self.file_map.save(path); }
+        self.update_index();
+        let result = self.file_map.save(path);
+        self.release_index();
+        result
+    }
+
+    // Compare two snapshots of a volume of this kind
+    pub fn diff_snapshots(kind: &VolumeKind, old_path: &str, new_path: &str) -> Result<Vec<DiffEntry>, Box<dyn Error>> {
+        let (mut old_map, mut new_map) = (FileMap::new(), FileMap::new());
+        old_map.read(old_path)?;
+        new_map.read(new_path)?;
+        Ok(match kind {
+            VolumeKind::Ntfs(_) => old_map.diff(&new_map), // file reference numbers are stable
+            VolumeKind::Folder(_) => old_map.diff_by_path(&new_map),
+        })
+    }
+
     // This is a helper function that opens a handle to the volume specified by the cDriveLetter parameter.
     fn open_drive(drive_letter: char) -> Foundation::HANDLE {
         unsafe{
@@ -75,6 +280,27 @@ impl Volume {
         }
     }
 
+    // Return statistics about the journal of a drive, None if it can not be queried
+    fn query_journal(drive: char) -> Option<Ioctl::USN_JOURNAL_DATA_V0> {
+        let h_vol = Self::open_drive(drive);
+        let mut ujd = Ioctl::USN_JOURNAL_DATA_V0::default();
+        let mut cd: u32 = 0;
+        let result = unsafe {
+            IO::DeviceIoControl(
+                h_vol,
+                Ioctl::FSCTL_QUERY_USN_JOURNAL,
+                None,
+                0,
+                Some(&mut ujd as *mut Ioctl::USN_JOURNAL_DATA_V0 as *mut c_void),
+                std::mem::size_of::<Ioctl::USN_JOURNAL_DATA_V0>() as u32,
+                Some(&mut cd),
+                None
+            )
+        };
+        Self::close_drive(h_vol);
+        result.ok().map(|_| ujd)
+    }
+
     // This is a helper function that close a handle.
     fn close_drive(h_vol: Foundation::HANDLE) {
         unsafe { 
@@ -83,16 +309,41 @@ impl Volume {
         }
     }
 
-    // Enumerate the MFT for all entries. Store the file reference numbers of any directories in the database.
+    // Build the database from scratch and serialize it to the .fd file
     pub fn build_index(&mut self) {
+        let started = Instant::now();
         #[cfg(debug_assertions)]
-        let sys_time = SystemTime::now();
-        #[cfg(debug_assertions)]
-        log_info(format!("{} Begin Volume::build_index", self.drive));
+        log_info(format!("{} Begin Volume::build_index", self.name));
 
         self.release_index();
+        self.kept_usage = None; // sizes are read again for the new map
+        self.sizes_read = false;
+
+        self.set_phase(IndexPhase::Enumerating);
+        match self.kind.clone() {
+            VolumeKind::Ntfs(drive) => self.build_from_mft(drive),
+            VolumeKind::Folder(root) => {
+                self.watch_folder(&root);
+                walker::walk(&root, &mut self.file_map, &self.progress);
+            },
+        }
+        self.entry_count = self.file_map.len();
+        self.last_build = Some(SystemTime::now());
+        self.metrics.build = Some(started.elapsed());
+        self.record_stats();
+
+        #[cfg(debug_assertions)]
+        log_info(format!("{} End Volume::build_index, use time: {:?} ms", self.name, started.elapsed().as_millis()));
 
-        let h_vol = Self::open_drive(self.drive);
+        self.set_phase(IndexPhase::Saving);
+        self.serialization_write()
+            .unwrap_or_else(|e| log_error(format!("{} Volume::serialization_write, error: {:?}", self.name, e)));
+        self.set_phase(IndexPhase::Idle);
+    }
+
+    // Enumerate the MFT for all entries. Store the file reference numbers of any directories in the database.
+    fn build_from_mft(&mut self, drive: char) {
+        let h_vol = Self::open_drive(drive);
 
         // Query, Return statistics about the journal on the current volume
         let mut cd: u32 = 0;
@@ -106,14 +357,14 @@ impl Volume {
                 std::mem::size_of::<Ioctl::USN_JOURNAL_DATA_V0>() as u32, 
                 Some(&mut cd), 
                 None
-            ).unwrap_or_else(|e| log_error(format!("{} Volume::build_index, error: {:?}", self.drive, e)));
+            ).unwrap_or_else(|e| log_error(format!("{} Volume::build_index, error: {:?}", drive, e)));
         };
 
         self.file_map.start_usn = self.ujd.NextUsn;
 
         // add the root directory
-        let sz_root = format!("{}:", self.drive);
-        self.file_map.insert(self.drive_frn, sz_root, 0);
+        let sz_root = format!("{}:", drive);
+        self.file_map.insert(self.drive_frn, sz_root, 0, 0);
 
         let mut med: Ioctl::MFT_ENUM_DATA_V0 = Ioctl::MFT_ENUM_DATA_V0 {
             StartFileReferenceNumber: 0,
@@ -136,6 +387,7 @@ impl Volume {
             ).is_ok() {
                 let mut record_ptr = data.as_ptr().offset(1) as *const Ioctl::USN_RECORD_V2;
                 let data_end = data.as_ptr() as usize + cb as usize;
+                let mut record_num = 0;
 
                 while (record_ptr as usize) < data_end {
                     let record = &*record_ptr;
@@ -145,97 +397,353 @@ impl Volume {
                     let file_name_list = std::slice::from_raw_parts(file_name_begin_ptr, file_name_length);
                     let file_name = String::from_utf16(file_name_list).unwrap_or(String::from("unknown"));
 
-                    self.file_map.insert(record.FileReferenceNumber, file_name, record.ParentFileReferenceNumber);
+                    // sizes are read when the disk usage view needs them, see usage_children
+                    self.file_map.insert(record.FileReferenceNumber, file_name, record.ParentFileReferenceNumber, 0);
                     record_ptr = (record_ptr as usize + record.RecordLength as usize) as *mut Ioctl::USN_RECORD_V2;
+                    record_num += 1;
                 }
 
+                self.add_records(record_num);
                 med.StartFileReferenceNumber = data[0];
             }
         }
 
-        #[cfg(debug_assertions)]
-        log_info(format!("{} End Volume::build_index, use time: {:?} ms", self.drive, sys_time.elapsed().unwrap_or_default().as_millis()));
-        
         Self::close_drive(h_vol);
-        self.serialization_write()
-            .unwrap_or_else(|e| log_error(format!("{} Volume::serialization_write, error: {:?}", self.drive, e)));
+    }
+
+    // Register a change notification for the whole tree of a folder root
+    fn watch_folder(&mut self, root: &str) {
+        if self.watch_handle != 0 { return; }
+        let root_path: Vec<u16> = root.encode_utf16().chain(std::iter::once(0)).collect();
+        unsafe {
+            match FileSystem::FindFirstChangeNotificationW(
+                windows::core::PCWSTR(root_path.as_ptr()),
+                true,
+                FileSystem::FILE_NOTIFY_CHANGE_FILE_NAME | FileSystem::FILE_NOTIFY_CHANGE_DIR_NAME,
+            ) {
+                Ok(handle) => self.watch_handle = handle.0 as isize,
+                Err(e) => log_error(format!("{} Volume::watch_folder, fall back to periodic refresh: {:?}", self.name, e)),
+            }
+        }
+    }
+
+    // Check whether a folder root needs to be walked again
+    fn folder_changed(&self) -> bool {
+        if self.watch_handle != 0 {
+            let handle = HANDLE(self.watch_handle as *mut c_void);
+            unsafe {
+                if Threading::WaitForSingleObject(handle, 0) == Foundation::WAIT_OBJECT_0 {
+                    let _ = FileSystem::FindNextChangeNotification(handle);
+                    return true;
+                }
+            }
+            return false;
+        }
+
+        let refresh_minutes = AppConfig::global()
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get_search_root_refresh();
+        match self.last_build {
+            Some(last_build) => last_build.elapsed().unwrap_or_default() >= Duration::from_secs(refresh_minutes as u64 * 60),
+            None => true,
+        }
     }
 
     // Clears the database
     pub fn release_index(&mut self) {
+        self.borrowed = false;
         if self.file_map.is_empty() {return;}
 
-        self.last_query = String::new();
-        self.last_search_num = 0;
+        self.reset_queries();
 
         #[cfg(debug_assertions)]
-        log_info(format!("{} Begin Volume::release_index", self.drive));
+        log_info(format!("{} Begin Volume::release_index", self.name));
+
+        // the folder sizes are kept, the index is saved with the file sizes they were summed from
+        if self.file_map.has_usage() {
+            match self.file_map.save(&Self::fd_path(&self.kind)) {
+                Ok(_) => self.kept_usage = self.file_map.take_usage(),
+                Err(e) => log_error(format!("{} Volume::release_index, error: {:?}", self.name, e)),
+            }
+        }
 
         self.file_map.clear();
     }
 
+    // forget the query states, they are invalid once the file map changed
+    fn reset_queries(&mut self) {
+        self.query_state = QueryState::default();
+        self.query_cache.clear();
+    }
+
+    // Make query the current one, reusing a cached state or narrowing the previous query when possible
+    // return true if the query state was restored from the cache
+    fn switch_query(&mut self, query: String) -> bool {
+        let last_state = std::mem::take(&mut self.query_state);
+        let mut restored = false;
+        if let Some(state) = self.query_cache.iter().position(|state| state.query == query)
+            .and_then(|pos| self.query_cache.remove(pos)) {
+            self.query_state = state;
+            restored = true;
+        } else if QueryState::refines(&query, &last_state.query) {
+            self.query_state = QueryState::narrow(query, &last_state);
+        } else {
+            self.query_state = QueryState::new(query);
+        }
+
+        if !last_state.query.is_empty() {
+            self.query_cache.push_front(last_state);
+            self.query_cache.truncate(QUERY_CACHE_SIZE);
+        }
+        restored
+    }
+
     // searching
     pub fn find(&mut self, query: String, batch: u8, sender: mpsc::Sender<Option<Vec<SearchResultItem>>>) {
-        #[cfg(debug_assertions)]
-        let sys_time = SystemTime::now();
+        let started = Instant::now();
 
         #[cfg(debug_assertions)]
-        log_info(format!("{} Begin Volume::Find {query}", self.drive));
+        log_info(format!("{} Begin Volume::Find {query}", self.name));
 
         if query.is_empty() { 
             let _ = sender.send(None);
             return;
         }
+        self.borrowed = false; // kept by the residency policy of the search window from now on
 
-        if self.last_query != query {
-            self.last_search_num = 0;
-            self.last_query = query.clone();
+        let mut result = Vec::new();
+        let new_query = self.query_state.query != query;
+        if new_query && self.switch_query(query.clone()) {
+            // a recent query, answer with what was found before, batch by batch as it was found
+            self.query_state.sent = 0;
         }
 
         if self.file_map.is_empty() { 
             self.serialization_read()
                 .unwrap_or_else(|e| {
-                    log_error(format!("{} Volume::serialization_write, error: {:?}", self.drive, e));
+                    log_error(format!("{} Volume::serialization_write, error: {:?}", self.name, e));
                     self.build_index();
                 });
+            self.set_phase(IndexPhase::Idle);
         };
 
         while self.stop_receiver.try_recv().is_ok() { } // clear channel before find
-        let (result, search_num) = self.file_map.search(&query, self.last_search_num, batch, &self.stop_receiver);
+        let state = &mut self.query_state;
+        if state.sent < state.matched.len() {
+            let end = state.matched.len().min(state.sent + batch as usize);
+            result = self.file_map.get_items(&state.matched[state.sent..end]);
+            state.sent = end;
+        }
+        let resumed = state.sent == state.matched.len(); // the search goes on once the known matches are out
+        if resumed && result.len() < batch as usize && state.next_candidate < state.candidates.len() {
+            let (found, search_num) = self.file_map.search_in(
+                &query, &state.candidates[state.next_candidate..], batch - result.len() as u8, &mut state.matched, &self.stop_receiver);
+            match found {
+                Some(mut found) => { result.append(&mut found); state.next_candidate += search_num; },
+                None => { return self.cancel_find(sender); },
+            }
+        }
+        if resumed && result.len() < batch as usize {
+            let (found, search_num) = self.file_map.search(
+                &query, state.scanned, batch - result.len() as u8, &mut state.matched, &self.stop_receiver);
+            match found {
+                Some(mut found) => { result.append(&mut found); state.scanned += search_num; },
+                None => { return self.cancel_find(sender); },
+            }
+        }
+        if resumed { state.sent = state.matched.len(); }
+
+        self.metrics.find = Some(started.elapsed());
 
         #[cfg(debug_assertions)]
-        log_info(format!("{} End Volume::Find {query}, use time: {:?} ms", self.drive, sys_time.elapsed().unwrap_or_default().as_millis()));
+        log_info(format!("{} End Volume::Find {query}, use time: {:?} ms", self.name, started.elapsed().as_millis()));
         
-        self.last_search_num += search_num;
+        if new_query && !result.is_empty() { self.hits += 1; }
+
+        let _ = sender.send(Some(result));
+    }
+
+    // read the released file map back for callers outside the search window, they call end_borrow when done
+    fn ensure_loaded(&mut self) {
+        if self.file_map.is_empty() { 
+            self.serialization_read()
+                .unwrap_or_else(|e| {
+                    log_error(format!("{} Volume::serialization_read, error: {:?}", self.name, e));
+                    self.build_index();
+                });
+            self.set_phase(IndexPhase::Idle);
+            self.borrowed = true;
+        };
+    }
 
-        let _ = sender.send(result);
+    // Release the file map again if it was only read for a caller outside the search window
+    pub fn end_borrow(&mut self) {
+        if self.borrowed { self.release_index(); }
+    }
+
+    // Search one batch of entries without touching the paging state of the search window, continuing after
+    // the key the last batch returned, None to start. The lock is meant to be dropped between batches.
+    // Return the matches and the key to continue after, None once all entries were searched, or None if stopped.
+    pub fn search_detached(&mut self, query: &str, after: Option<FileKey>, stop_receiver: &mpsc::Receiver<()>)
+        -> Option<(Vec<SearchResultItem>, Option<FileKey>)> {
+        self.ensure_loaded();
+        self.file_map.search_from(query, after, DETACHED_BATCH, stop_receiver)
+    }
+
+    // files of the volume grouped by lowercased name, used by the duplicate finder
+    pub fn name_groups(&mut self) -> HashMap<String, Vec<u64>> {
+        self.ensure_loaded();
+        self.file_map.name_groups()
+    }
+
+    // full paths of the files with one of the given lowercased extensions, e.g. the archives to look into
+    pub fn paths_with_extensions(&mut self, extensions: &[&str]) -> Vec<String> {
+        self.ensure_loaded();
+        self.file_map.paths_with_extensions(extensions)
+    }
+
+    // the index and full path of each file that is still in the index
+    pub fn full_paths(&mut self, indexes: &[u64]) -> Vec<(u64, String)> {
+        self.ensure_loaded();
+        indexes.iter().filter_map(|index| Some((*index, self.file_map.get_full_path(index)?))).collect()
+    }
+
+    // The children of a folder for the disk usage view, the root of the volume for None.
+    // Return the children and the size of the folder.
+    pub fn usage_children(&mut self, index: Option<u64>) -> (Vec<UsageEntry>, u64) {
+        self.ensure_loaded();
+        if !self.file_map.has_usage() {
+            if matches!(self.kind, VolumeKind::Ntfs(_)) && !self.sizes_read {
+                self.file_map.fill_sizes(); // a walked root has them from the walk
+                self.sizes_read = true;
+            }
+            self.file_map.build_usage();
+            self.memory_size = self.file_map.memory_size();
+        }
+        let index = index.unwrap_or(match self.kind {
+            VolumeKind::Ntfs(_) => self.drive_frn,
+            VolumeKind::Folder(_) => walker::ROOT_INDEX,
+        });
+        (self.file_map.usage_children(&index), self.file_map.usage_of(&index).unwrap_or(0))
+    }
+
+    // The children of a folder for the path listing of the search window, dir ends with a separator.
+    // None if the folder is not in this volume or the index is released, the file system is asked then.
+    pub fn folder_entries(&self, dir: &str) -> Option<Vec<FolderEntry>> {
+        if !self.is_loaded() { return None; }
+        let index = match &self.kind {
+            VolumeKind::Ntfs(drive) => {
+                if !dir.get(..2)?.eq_ignore_ascii_case(&format!("{}:", drive)) { return None; }
+                // the index is keyed by file reference number, the folder itself tells its own
+                Self::file_reference_number(dir)?
+            },
+            VolumeKind::Folder(root) => {
+                // a walked root numbers its entries itself, the folder is found by its names
+                let names = dir.split('\\').filter(|name| !name.is_empty()).collect::<Vec<_>>();
+                let root_names = root.split(['\\', '/']).filter(|name| !name.is_empty()).collect::<Vec<_>>();
+                if names.len() < root_names.len() { return None; }
+                let (first, names) = names.split_at(root_names.len());
+                if !first.iter().zip(&root_names).all(|(a, b)| a.eq_ignore_ascii_case(b)) { return None; }
+                self.file_map.find_path(&walker::ROOT_INDEX, names)?
+            },
+        };
+        Some(self.file_map.folder_entries(&index))
+    }
+
+    // The file reference number of a file or folder, as the journal and the MFT enumeration report it
+    fn file_reference_number(path: &str) -> Option<u64> {
+        let wide_path = path.encode_utf16().chain(std::iter::once(0)).collect::<Vec<u16>>();
+        unsafe {
+            let handle = FileSystem::CreateFileW(
+                windows::core::PCWSTR(wide_path.as_ptr()),
+                0, // only the attributes are read
+                FileSystem::FILE_SHARE_READ | FileSystem::FILE_SHARE_WRITE | FileSystem::FILE_SHARE_DELETE,
+                None,
+                FileSystem::OPEN_EXISTING,
+                FileSystem::FILE_FLAG_BACKUP_SEMANTICS, // needed to open a folder
+                None
+            ).ok()?;
+            let mut information = FileSystem::BY_HANDLE_FILE_INFORMATION::default();
+            let result = FileSystem::GetFileInformationByHandle(handle, &mut information);
+            Self::close_drive(handle);
+            result.ok()?;
+            Some(((information.nFileIndexHigh as u64) << 32) | information.nFileIndexLow as u64)
+        }
+    }
+
+    // the state may hold matches that were never sent, start the query over next time
+    fn cancel_find(&mut self, sender: mpsc::Sender<Option<Vec<SearchResultItem>>>) {
+        self.query_state = QueryState::default();
+        let _ = sender.send(None);
     }
 
     // update index, add new file, remove deleted file
     pub fn update_index(&mut self) {
+        let started = Instant::now();
+
         #[cfg(debug_assertions)]
-        log_info(format!("{} Begin Volume::update_index", self.drive));
+        log_info(format!("{} Begin Volume::update_index", self.name));
+
+        self.reset_queries(); // the journal may add or remove files
+        self.borrowed = false; // kept by the residency policy of the search window from now on
+
+        // the tree of a folder root before it is walked again, compared by path afterwards
+        let mut old_map = None;
+        if let VolumeKind::Folder(_) = self.kind {
+            // a walked root has no journal, rebuild it when the tree changed
+            if self.folder_changed() {
+                let mut map = std::mem::replace(&mut self.file_map, FileMap::new());
+                if map.is_empty() && map.read(&Self::fd_path(&self.kind)).is_err() { map.clear(); }
+                if !map.is_empty() { old_map = Some(map); }
+                self.build_index();
+            }
+        }
 
         if self.file_map.is_empty() { 
             self.serialization_read()
                 .unwrap_or_else(|e: Box<dyn Error>| {
-                    log_error(format!("{} Volume::serialization_write, error: {:?}", self.drive, e));
+                    log_error(format!("{} Volume::serialization_write, error: {:?}", self.name, e));
                     self.build_index();
                 });
         };
 
+        let drive = match self.kind {
+            VolumeKind::Ntfs(drive) => drive,
+            VolumeKind::Folder(_) => {
+                if let Some(old_map) = old_map {
+                    let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+                    let events = old_map.diff_by_path(&self.file_map).into_iter().map(|entry| match entry {
+                        DiffEntry::Added(path) => ChangeEvent { time, kind: ChangeKind::Created, path, old_path: String::new() },
+                        DiffEntry::Removed(path) => ChangeEvent { time, kind: ChangeKind::Deleted, path, old_path: String::new() },
+                        DiffEntry::Renamed(old_path, path) => ChangeEvent { time, kind: ChangeKind::Renamed, path, old_path },
+                    }).collect::<Vec<_>>();
+                    Self::log_changes(&events);
+                }
+                self.metrics.update = Some(started.elapsed());
+                self.metrics.update_records = 0;
+                self.set_phase(IndexPhase::Idle);
+                return;
+            },
+        };
+
+        self.set_phase(IndexPhase::Updating);
+        let mut update_records = 0;
+        let mut changes = JournalChanges::default();
+
         let mut data = [0i64; 0x10000];
         let mut cb: u32 = 0;
         let mut rujd: Ioctl::READ_USN_JOURNAL_DATA_V0 = Ioctl::READ_USN_JOURNAL_DATA_V0 {
                 StartUsn: self.file_map.start_usn,
-                ReasonMask: Ioctl::USN_REASON_FILE_CREATE | Ioctl::USN_REASON_FILE_DELETE | Ioctl::USN_REASON_RENAME_NEW_NAME | Ioctl::USN_REASON_RENAME_OLD_NAME,
+                ReasonMask: Ioctl::USN_REASON_FILE_CREATE | Ioctl::USN_REASON_FILE_DELETE | Ioctl::USN_REASON_RENAME_NEW_NAME | Ioctl::USN_REASON_RENAME_OLD_NAME
+                    | Ioctl::USN_REASON_DATA_OVERWRITE | Ioctl::USN_REASON_DATA_EXTEND | Ioctl::USN_REASON_DATA_TRUNCATION,
                 ReturnOnlyOnClose: 0,
                 Timeout: 0,
                 BytesToWaitFor: 0,
                 UsnJournalID: self.ujd.UsnJournalID,
         };
 
-        let h_vol = Self::open_drive(self.drive);
+        let h_vol = Self::open_drive(drive);
 
         unsafe{
             while IO::DeviceIoControl(
@@ -251,6 +759,7 @@ impl Volume {
                 if cb == 8 { break };
                 let mut record_ptr = data.as_ptr().offset(1) as *const Ioctl::USN_RECORD_V2;
                 let data_end = data.as_ptr() as usize + cb as usize;
+                let mut record_num = 0;
                 
                 while (record_ptr as usize) < data_end {
                     let record = &*record_ptr;
@@ -259,60 +768,116 @@ impl Volume {
                     let file_name_list = std::slice::from_raw_parts(file_name_begin_ptr, file_name_length);
                     let file_name = String::from_utf16(file_name_list).unwrap_or(String::from("unknown"));
                     
-                    if record.Reason & (Ioctl::USN_REASON_FILE_CREATE | Ioctl::USN_REASON_RENAME_NEW_NAME) != 0 {
-                        self.file_map.insert(record.FileReferenceNumber, file_name, record.ParentFileReferenceNumber);
-                    } else { // Ioctl::USN_REASON_FILE_DELETE | Ioctl::USN_REASON_RENAME_OLD_NAME
-                        self.file_map.remove(&record.FileReferenceNumber);
+                    let frn = record.FileReferenceNumber;
+                    let time = change_log::filetime_to_unix(record.TimeStamp);
+
+                    // reasons add up until the file is closed, a deleted file may still carry its create reason
+                    if record.Reason & Ioctl::USN_REASON_FILE_DELETE != 0 {
+                        changes.deleted(frn, time, self.file_map.get_full_path(&frn));
+                        self.file_map.remove(&frn);
+                    } else if record.Reason & (Ioctl::USN_REASON_FILE_CREATE | Ioctl::USN_REASON_RENAME_NEW_NAME) != 0 {
+                        self.file_map.insert(frn, file_name, record.ParentFileReferenceNumber, 0);
+                        let path = self.file_map.get_full_path(&frn);
+                        // while the disk usage view keeps folder sizes, new files are read one by one
+                        if self.file_map.has_usage() && record.FileAttributes & FileSystem::FILE_ATTRIBUTE_DIRECTORY.0 == 0 {
+                            let size = path.as_ref().and_then(|path| fs::metadata(path).ok()).map(|metadata| metadata.len());
+                            if let Some(size) = size { self.file_map.set_size(&frn, size); }
+                        }
+                        if record.Reason & Ioctl::USN_REASON_FILE_CREATE != 0 {
+                            changes.created(frn, time, path);
+                        } else {
+                            changes.renamed_to(frn, time, path);
+                        }
+                    } else if record.Reason & Ioctl::USN_REASON_RENAME_OLD_NAME != 0 {
+                        changes.renamed_from(frn, self.file_map.get_full_path(&frn));
+                        self.file_map.remove(&frn);
+                    } else {
+                        // only data reasons are left, the index itself does not change
+                        changes.modified(frn, time, self.file_map.get_full_path(&frn));
                     }
 
                     record_ptr = (record_ptr as usize + record.RecordLength as usize) as *mut Ioctl::USN_RECORD_V2;
+                    record_num += 1;
                 }
                 
+                self.add_records(record_num);
+                update_records += record_num;
                 rujd.StartUsn = data[0];
             }
         }
         self.file_map.start_usn = rujd.StartUsn;
         Self::close_drive(h_vol);
+        Self::log_changes(&changes.into_events());
+        self.metrics.update = Some(started.elapsed());
+        self.metrics.update_records = update_records;
+
+        #[cfg(debug_assertions)]
+        log_info(format!("{} End Volume::update_index, {} records, use time: {:?} ms", self.name, update_records, started.elapsed().as_millis()));
+
+        self.set_phase(IndexPhase::Idle);
+    }
+
+    fn log_changes(events: &[ChangeEvent]) {
+        if events.is_empty() { return; }
+        ChangeLog::global()
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .append(events)
+            .unwrap_or_else(|e| log_error(format!("Volume::log_changes, error: {:?}", e)));
     }
 
     // serializate file_map to reduce memory usage
     fn serialization_write(&mut self) -> Result<(), io::Error> {
+        let started = Instant::now();
         #[cfg(debug_assertions)]
-        let sys_time = SystemTime::now();
-        #[cfg(debug_assertions)]
-        log_info(format!("{} Begin Volume::serialization_write", self.drive));
+        log_info(format!("{} Begin Volume::serialization_write", self.name));
 
         if self.file_map.is_empty() {return Ok(())};
         
         let file_path = file_util::get_userdata_path();
         if !file_path.exists() { fs::create_dir(&file_path)?; }
-        let file_name = format!("{}/{}.fd", file_path.to_str().unwrap_or("."), self.drive);
+        let file_name = Self::fd_path(&self.kind);
 
         self.file_map.save(&file_name)?;
+        self.metrics.write = Some(started.elapsed());
 
         self.release_index();
 
         #[cfg(debug_assertions)]
-        log_info(format!("{} End Volume::serialization_write, use time: {:?} ms", self.drive, sys_time.elapsed().unwrap_or_default().as_millis()));
+        log_info(format!("{} End Volume::serialization_write, use time: {:?} ms", self.name, started.elapsed().as_millis()));
 
         Ok(())
     }
 
     // deserializate file_map from file
     fn serialization_read(&mut self) -> Result<(), Box<dyn Error>> {
+        let started = Instant::now();
         #[cfg(debug_assertions)]
-        let sys_time = SystemTime::now();
-        #[cfg(debug_assertions)]
-        log_info(format!("{} Begin Volume::serialization_read", self.drive));
+        log_info(format!("{} Begin Volume::serialization_read", self.name));
+
+        self.set_phase(IndexPhase::Loading);
         
-        let file_path = file_util::get_userdata_path();
-        let file_name = format!("{}/{}.fd", file_path.to_str().unwrap_or("."), self.drive);
+        let file_name = Self::fd_path(&self.kind);
 
         self.file_map.read(&file_name)?;
+        if let Some(usage) = self.kept_usage.take() { self.file_map.restore_usage(usage); }
+        self.entry_count = self.file_map.len();
+        self.metrics.read = Some(started.elapsed());
+        self.record_stats();
 
         #[cfg(debug_assertions)]
-        log_info(format!("{} End Volume::serialization_read, use time: {:?} ms", self.drive, sys_time.elapsed().unwrap_or_default().as_millis()));
+        log_info(format!("{} End Volume::serialization_read, use time: {:?} ms", self.name, started.elapsed().as_millis()));
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+impl Drop for Volume {
+    fn drop(&mut self) {
+        if self.watch_handle != 0 {
+            unsafe {
+                let _ = FileSystem::FindCloseChangeNotification(HANDLE(self.watch_handle as *mut c_void));
+            }
+        }
+    }
+}
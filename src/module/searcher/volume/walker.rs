@@ -0,0 +1,39 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use super::file_map::FileMap;
+use super::IndexProgress;
+
+// index of the root entry, 0 is reserved to end the parent chain
+pub const ROOT_INDEX: u64 = 1;
+
+// Walk the directory tree under root and insert every entry into the database.
+// Entries are numbered in walk order, so indexes are only stable within one walk.
+pub fn walk(root: &str, file_map: &mut FileMap, progress: &Mutex<IndexProgress>) {
+    let root_name = root.trim_end_matches(['\\', '/']).to_string();
+    file_map.insert(ROOT_INDEX, root_name, 0, 0);
+
+    let mut next_index = ROOT_INDEX + 1;
+    let mut dirs: Vec<(u64, PathBuf)> = vec![(ROOT_INDEX, PathBuf::from(root))];
+    while let Some((parent_index, dir)) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue, // skip folders we are not allowed to read
+        };
+
+        let first_index = next_index;
+        for entry in entries.flatten() {
+            let index = next_index;
+            next_index += 1;
+
+            // file_type does not follow symlinks, so linked folders can not cause a loop
+            let is_dir = entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false);
+            if is_dir { dirs.push((index, entry.path())); }
+            // the metadata comes with the directory listing, no extra call per file
+            let size = if is_dir { 0 } else { entry.metadata().map(|metadata| metadata.len()).unwrap_or(0) };
+            file_map.insert(index, entry.file_name().to_string_lossy().to_string(), parent_index, size);
+        }
+        progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).records += (next_index - first_index) as usize;
+    }
+}
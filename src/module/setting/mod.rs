@@ -1,16 +1,18 @@
 use std::error::Error;
+use std::rc::Rc;
 use std::sync::mpsc;
 use crossbeam;
 use global_hotkey::hotkey::HotKey;
 use i_slint_backend_winit::WinitWindowAccessor;
-use slint::ComponentHandle;
+use slint::{ComponentHandle, Model, VecModel};
+use wfd::DialogParams;
 use windows::Win32::UI::WindowsAndMessaging;
 
-use crate::core::application::{AppMessage, app_config::AppConfig};
+use crate::core::application::{AppMessage, app_config::{AppConfig, Alias}};
 use crate::util::net_util::Updater;
 use crate::util::{file_util, log_util};
-use crate::ui::SettingWindow;
-use crate::module::{Module, ModuleMessage};
+use crate::ui::{SettingWindow, SearchRoot_slint, SearchRootState, Alias_slint};
+use crate::module::{Module, ModuleMessage, searcher::{ClipHistory, SearchHistory}};
 
 
 pub struct Setting {
@@ -87,6 +89,27 @@ impl Setting {
         
         setting_win.set_zoom_delta(app_config.get_zoom_delta().to_string().into());
 
+        let search_roots = app_config.get_search_roots().into_iter().map(|root| {
+            SearchRoot_slint { path: root.into(), state: SearchRootState::Waiting, entries: 0 }
+        }).collect::<Vec<_>>();
+        setting_win.set_search_roots(Rc::new(VecModel::from(search_roots)).into());
+        setting_win.set_index_residency(app_config.get_index_residency() as i32);
+        setting_win.set_index_idle_minutes(app_config.get_index_idle_minutes().to_string().into());
+        setting_win.set_index_memory_budget(app_config.get_index_memory_budget().to_string().into());
+        setting_win.set_query_server(app_config.get_query_server());
+        setting_win.set_query_server_port(app_config.get_query_server_port().to_string().into());
+        setting_win.set_http_server(app_config.get_http_server());
+        setting_win.set_http_server_port(app_config.get_http_server_port().to_string().into());
+        setting_win.set_search_archives(app_config.get_search_archives());
+        setting_win.set_content_search(app_config.get_content_search());
+        setting_win.set_content_extensions(app_config.get_content_extensions().join(", ").into());
+        setting_win.set_content_max_kb(app_config.get_content_max_kb().to_string().into());
+        setting_win.set_aliases(Rc::new(VecModel::from(Self::alias_rows(&app_config.get_aliases()))).into());
+        setting_win.set_search_history(app_config.get_search_history());
+        setting_win.set_clip_history(app_config.get_clip_history());
+        setting_win.set_clip_history_size(app_config.get_clip_history_size().to_string().into());
+        setting_win.set_clip_exclusions(app_config.get_clip_exclusions().join(", ").into());
+
         { // code for setting change
             { // power boot
                 setting_win.on_power_boot_changed(move |power_boot| {
@@ -131,6 +154,302 @@ impl Setting {
                 });
             }
 
+            { // search roots
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_add_search_root(move || {
+                    let setting_win_clone = setting_win_clone.clone();
+                    std::thread::spawn(move || {
+                        let params = DialogParams {
+                            title: "Select a folder to index",
+                            options: wfd::FOS_PICKFOLDERS,
+                            ..Default::default()
+                        };
+                        if let Ok(dialog_result) = wfd::open_dialog(params) {
+                            let root = dialog_result.selected_file_path.to_string_lossy().to_string();
+                            let add_result = AppConfig::global()
+                                .lock()
+                                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                                .add_search_root(root.clone());
+                            match add_result {
+                                Ok(_) => {
+                                    setting_win_clone.upgrade_in_event_loop(move |setting_win| {
+                                        let search_roots = setting_win.get_search_roots();
+                                        if let Some(search_roots) = search_roots.as_any().downcast_ref::<VecModel<SearchRoot_slint>>() {
+                                            search_roots.push(SearchRoot_slint { path: root.into(), state: SearchRootState::Waiting, entries: 0 });
+                                        }
+                                    }).unwrap_or_else(|e| log_util::log_error(format!("Failed to show new search root: {:?}", e)));
+                                },
+                                Err(e) => log_util::log_error(format!("Failed to add search root: {:?}", e)),
+                            }
+                        }
+                    });
+                });
+
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_remove_search_root(move |root| {
+                    AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .remove_search_root(root.as_str())
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to remove search root: {:?}", e)));
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        let search_roots = setting_win.get_search_roots();
+                        if let Some(search_roots) = search_roots.as_any().downcast_ref::<VecModel<SearchRoot_slint>>() {
+                            if let Some(index) = search_roots.iter().position(|r| r.path == root) {
+                                search_roots.remove(index);
+                            }
+                        }
+                    }
+                });
+            }
+
+            { // index residency
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_index_residency_changed(move |residency| {
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        setting_win.set_index_residency(residency);
+                    }
+                    AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .set_index_residency(residency as u8)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set index residency: {:?}", e)));
+                });
+
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_index_idle_minutes_changed(move |minutes| {
+                    let minutes_int = minutes.parse::<u32>().unwrap_or(10).max(1);
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        setting_win.set_index_idle_minutes(minutes_int.to_string().into());
+                    }
+                    AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .set_index_idle_minutes(minutes_int)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set index idle minutes: {:?}", e)));
+                });
+
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_index_memory_budget_changed(move |budget| {
+                    let budget_int = budget.parse::<u32>().unwrap_or(0);
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        setting_win.set_index_memory_budget(budget_int.to_string().into());
+                    }
+                    AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .set_index_memory_budget(budget_int)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set index memory budget: {:?}", e)));
+                });
+            }
+
+            { // query server
+                setting_win.on_query_server_changed(move |enable| {
+                    AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .set_query_server(enable)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set query server: {:?}", e)));
+                });
+
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_query_server_port_changed(move |port| {
+                    let mut app_config = AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    let port_int = port.parse::<u16>().unwrap_or(app_config.get_query_server_port());
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        setting_win.set_query_server_port(port_int.to_string().into());
+                    }
+                    app_config
+                        .set_query_server_port(port_int)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set query server port: {:?}", e)));
+                });
+
+                setting_win.on_http_server_changed(move |enable| {
+                    AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .set_http_server(enable)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set http server: {:?}", e)));
+                });
+
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_http_server_port_changed(move |port| {
+                    let mut app_config = AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    let port_int = port.parse::<u16>().unwrap_or(app_config.get_http_server_port());
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        setting_win.set_http_server_port(port_int.to_string().into());
+                    }
+                    app_config
+                        .set_http_server_port(port_int)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set http server port: {:?}", e)));
+                });
+            }
+
+            { // search archives
+                setting_win.on_search_archives_changed(move |enable| {
+                    AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .set_search_archives(enable)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set search archives: {:?}", e)));
+                });
+            }
+
+            { // content search
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_content_search_changed(move |enable| {
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        setting_win.set_content_search(enable);
+                    }
+                    AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .set_content_search(enable)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set content search: {:?}", e)));
+                });
+
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_content_extensions_changed(move |extensions| {
+                    let extensions = extensions.split(',')
+                        .map(|extension| extension.trim().trim_start_matches('.').to_lowercase())
+                        .filter(|extension| !extension.is_empty())
+                        .collect::<Vec<_>>();
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        setting_win.set_content_extensions(extensions.join(", ").into());
+                    }
+                    AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .set_content_extensions(extensions)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set content extensions: {:?}", e)));
+                });
+
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_content_max_kb_changed(move |max_kb| {
+                    let mut app_config = AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    let max_kb_int = max_kb.parse::<u32>().unwrap_or(app_config.get_content_max_kb());
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        setting_win.set_content_max_kb(max_kb_int.to_string().into());
+                    }
+                    app_config
+                        .set_content_max_kb(max_kb_int)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set content max size: {:?}", e)));
+                });
+            }
+
+            { // aliases
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_add_alias(move |keyword, target, dir| {
+                    let (keyword, target) = (keyword.trim().to_string(), target.trim().to_string());
+                    if keyword.is_empty() || target.is_empty() { return; }
+                    // a folder template like "D:\projects\{query}" counts by the folder before the placeholder
+                    let folder_part = target.split("{query}").next().unwrap_or_default();
+                    let is_folder = !keyword.starts_with('>') && !target.contains("://") && std::path::Path::new(folder_part).is_dir();
+                    let mut app_config = AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    app_config
+                        .add_alias(Alias { keyword, target, dir: dir.trim().to_string(), is_folder })
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to add alias: {:?}", e)));
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        setting_win.set_aliases(Rc::new(VecModel::from(Self::alias_rows(&app_config.get_aliases()))).into());
+                    }
+                });
+
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_remove_alias(move |keyword| {
+                    let mut app_config = AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    app_config
+                        .remove_alias(keyword.as_str())
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to remove alias: {:?}", e)));
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        setting_win.set_aliases(Rc::new(VecModel::from(Self::alias_rows(&app_config.get_aliases()))).into());
+                    }
+                });
+            }
+
+            { // search history
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_search_history_changed(move |enable| {
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        setting_win.set_search_history(enable);
+                    }
+                    AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .set_search_history(enable)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set search history: {:?}", e)));
+                });
+
+                setting_win.on_clear_search_history(|| {
+                    SearchHistory::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .clear()
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to clear search history: {:?}", e)));
+                });
+            }
+
+            { // clipboard history
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_clip_history_changed(move |enable| {
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        setting_win.set_clip_history(enable);
+                    }
+                    AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .set_clip_history(enable)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set clipboard history: {:?}", e)));
+                });
+
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_clip_history_size_changed(move |size| {
+                    let mut app_config = AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    let size_int = size.parse::<u32>().unwrap_or(app_config.get_clip_history_size()).max(1);
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        setting_win.set_clip_history_size(size_int.to_string().into());
+                    }
+                    app_config
+                        .set_clip_history_size(size_int)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set clipboard history size: {:?}", e)));
+                });
+
+                let setting_win_clone = setting_win.as_weak();
+                setting_win.on_clip_exclusions_changed(move |exclusions| {
+                    let exclusions = exclusions.split(',')
+                        .map(|exclusion| exclusion.trim().to_lowercase())
+                        .filter(|exclusion| !exclusion.is_empty())
+                        .collect::<Vec<_>>();
+                    if let Some(setting_win) = setting_win_clone.upgrade() {
+                        setting_win.set_clip_exclusions(exclusions.join(", ").into());
+                    }
+                    AppConfig::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .set_clip_exclusions(exclusions)
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to set clipboard exclusions: {:?}", e)));
+                });
+
+                setting_win.on_clear_clip_history(|| {
+                    ClipHistory::global()
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .clear()
+                        .unwrap_or_else(|e| log_util::log_error(format!("Failed to clear clipboard history: {:?}", e)));
+                });
+            }
+
             {// shortcut
                 let setting_win_clone = setting_win.as_weak();
                 let msg_sender = msg_sender.clone();
@@ -262,4 +581,12 @@ impl Setting {
             setting_win
         })
     }
+
+    fn alias_rows(aliases: &[Alias]) -> Vec<Alias_slint> {
+        aliases.iter().map(|alias| Alias_slint {
+            keyword: alias.keyword.as_str().into(),
+            target: alias.target.as_str().into(),
+            dir: alias.dir.as_str().into(),
+        }).collect()
+    }
 }
\ No newline at end of file
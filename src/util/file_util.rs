@@ -1,14 +1,18 @@
 use std::error::Error;
 use std::ffi::c_void;
 use std::process::Command;
+use std::os::windows::process::CommandExt;
 use std::{ptr, mem, fs, io, env};
 
 use slint::{SharedPixelBuffer, Rgba8Pixel};
 
 use windows::core::PCWSTR;
-use windows::Win32::Foundation::{HWND, BOOL};
+use windows::Win32::Foundation::{HWND, BOOL, HANDLE, POINT};
 use windows::Win32::Storage::FileSystem::FILE_ATTRIBUTE_NORMAL;
-use windows::Win32::UI::Shell::{SHGetFileInfoW, SHFILEINFOW, SHGFI_ICON, ShellExecuteW};
+use windows::Win32::System::DataExchange::{OpenClipboard, EmptyClipboard, SetClipboardData, CloseClipboard};
+use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GlobalFree, GMEM_MOVEABLE};
+use windows::Win32::UI::Shell::{SHGetFileInfoW, SHFILEINFOW, SHGFI_ICON, ShellExecuteW, DROPFILES};
+use windows::Win32::UI::Shell::{SHFileOperationW, SHFILEOPSTRUCTW, FO_DELETE, FOF_ALLOWUNDO, FOF_WANTNUKEWARNING, SHObjectProperties, SHOP_FILEPATH};
 use windows::Win32::UI::WindowsAndMessaging::{ICONINFO, GetIconInfo, DestroyIcon, HICON, SW_SHOWNORMAL};
 use windows::Win32::Graphics::Gdi::{self, DeleteObject, GetBitmapBits, BITMAP, BITMAPINFOHEADER, HBITMAP, HGDIOBJ};
 
@@ -78,6 +82,158 @@ pub fn open_file_admin(file_full_name: String) {
     };
 }
 
+// Put the full path of a file on the clipboard as text
+pub fn copy_path(path: &str) -> Result<(), Box<dyn Error>> {
+    arboard::Clipboard::new()?.set_text(path.to_string())?;
+    Ok(())
+}
+
+// Put a file on the clipboard like explorer does, pasting it makes a copy
+#[cfg(windows)]
+pub fn copy_file_to_clipboard(path: &str) -> Result<(), Box<dyn Error>> {
+    const CF_HDROP: u32 = 15;
+    let file_list: Vec<u16> = path.encode_utf16().chain([0, 0]).collect(); // a list of names ended by an empty one
+    let header_size = mem::size_of::<DROPFILES>();
+    let header = DROPFILES { pFiles: header_size as u32, pt: POINT::default(), fNC: BOOL(0), fWide: BOOL(1) };
+    unsafe {
+        let h_mem = GlobalAlloc(GMEM_MOVEABLE, header_size + file_list.len() * 2)?;
+        let mem_ptr = GlobalLock(h_mem) as *mut u8;
+        if mem_ptr.is_null() {
+            let _ = GlobalFree(h_mem);
+            return Err("Failed to lock the clipboard memory.".into());
+        }
+        ptr::copy_nonoverlapping(&header as *const DROPFILES as *const u8, mem_ptr, header_size);
+        ptr::copy_nonoverlapping(file_list.as_ptr() as *const u8, mem_ptr.add(header_size), file_list.len() * 2);
+        let _ = GlobalUnlock(h_mem);
+
+        OpenClipboard(HWND(ptr::null_mut()))?;
+        let result = EmptyClipboard().and_then(|_| SetClipboardData(CF_HDROP, HANDLE(h_mem.0)));
+        let _ = CloseClipboard();
+        if result.is_err() { let _ = GlobalFree(h_mem); } // owned by the clipboard once set
+        result?;
+    }
+    Ok(())
+}
+
+// Put a file on the clipboard as an uri list, which file managers paste as a copy
+#[cfg(not(windows))]
+pub fn copy_file_to_clipboard(path: &str) -> Result<(), Box<dyn Error>> {
+    use std::io::Write;
+    let uri = "file://".to_string() + &path.bytes().map(|byte| {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) { (byte as char).to_string() }
+        else { format!("%{:02X}", byte) }
+    }).collect::<String>();
+    let (program, args) = if env::var_os("WAYLAND_DISPLAY").is_some() { ("wl-copy", ["--type", "text/uri-list"].as_slice()) }
+        else { ("xclip", ["-selection", "clipboard", "-t", "text/uri-list"].as_slice()) };
+    let mut child = Command::new(program).args(args).stdin(std::process::Stdio::piped()).spawn()?;
+    child.stdin.take().ok_or("No stdin to write the uri list to.")?.write_all(uri.as_bytes())?;
+    child.wait()?; // both keep serving the clipboard in the background
+    Ok(())
+}
+
+// Ask which program opens a file
+#[cfg(windows)]
+pub fn open_with(path: &str) -> Result<(), Box<dyn Error>> {
+    let file_path: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
+    let openas_str: Vec<u16> = "openas".encode_utf16().chain(std::iter::once(0)).collect();
+    let instance = unsafe {
+        ShellExecuteW(
+            HWND(std::ptr::null_mut()),
+            PCWSTR(openas_str.as_ptr()),
+            PCWSTR(file_path.as_ptr()),
+            PCWSTR::null(),
+            PCWSTR::null(),
+            SW_SHOWNORMAL
+        )
+    };
+    if instance.0 as isize <= 32 { return Err(format!("ShellExecuteW failed with {}", instance.0 as isize).into()); }
+    Ok(())
+}
+
+// There is no common program chooser, the file is opened with its default program
+#[cfg(not(windows))]
+pub fn open_with(path: &str) -> Result<(), Box<dyn Error>> {
+    Command::new("xdg-open").arg(path).spawn()?;
+    Ok(())
+}
+
+// Rename a file in its folder, return its new path
+pub fn rename_file(path: &str, new_name: &str) -> Result<String, Box<dyn Error>> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() || new_name == "." || new_name == ".." || new_name.contains(['\\', '/']) {
+        return Err(format!("Invalid file name: {}", new_name).into());
+    }
+    let old_path = std::path::Path::new(path);
+    let new_path = old_path.with_file_name(new_name);
+    if new_path.exists() { return Err(format!("{} exists already.", new_path.display()).into()); }
+    fs::rename(old_path, &new_path)?;
+    Ok(new_path.to_string_lossy().to_string())
+}
+
+// Move a file or folder to the recycle bin, the shell asks first if it is too large for the bin
+#[cfg(windows)]
+pub fn move_to_recycle_bin(path: &str) -> Result<(), Box<dyn Error>> {
+    let file_list: Vec<u16> = path.encode_utf16().chain([0, 0]).collect(); // a list of names ended by an empty one
+    let mut file_op = SHFILEOPSTRUCTW {
+        wFunc: FO_DELETE,
+        pFrom: PCWSTR(file_list.as_ptr()),
+        fFlags: (FOF_ALLOWUNDO.0 | FOF_WANTNUKEWARNING.0) as u16,
+        ..Default::default()
+    };
+    let result = unsafe { SHFileOperationW(&mut file_op) };
+    if result != 0 { return Err(format!("SHFileOperationW failed with {:#x}", result).into()); }
+    if file_op.fAnyOperationsAborted.as_bool() { return Err("Moving to the recycle bin was canceled.".into()); }
+    Ok(())
+}
+
+// The trash of freedesktop, through gio which ships with most desktops
+#[cfg(not(windows))]
+pub fn move_to_recycle_bin(path: &str) -> Result<(), Box<dyn Error>> {
+    let status = Command::new("gio").arg("trash").arg(path).status()?;
+    if !status.success() { return Err(format!("gio trash failed with {}", status).into()); }
+    Ok(())
+}
+
+// Show the properties dialog of the shell
+#[cfg(windows)]
+pub fn show_properties(path: &str) -> Result<(), Box<dyn Error>> {
+    let file_path: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
+    let shown = unsafe { SHObjectProperties(HWND(ptr::null_mut()), SHOP_FILEPATH, PCWSTR(file_path.as_ptr()), PCWSTR::null()) };
+    if !shown.as_bool() { return Err(format!("Failed to show the properties of {}", path).into()); }
+    Ok(())
+}
+
+// There is no common properties dialog, the folder of the file is opened instead
+#[cfg(not(windows))]
+pub fn show_properties(path: &str) -> Result<(), Box<dyn Error>> {
+    let folder = std::path::Path::new(path).parent().ok_or("No parent folder.")?;
+    Command::new("xdg-open").arg(folder).spawn()?;
+    Ok(())
+}
+
+// Run a command line in a console that stays open to show its output, in dir or the home folder
+pub fn run_command(command_line: String, dir: String) -> Result<(), Box<dyn Error>> {
+    let dir = if dir.is_empty() { env::var("USERPROFILE").unwrap_or_else(|_| ".".to_string()) } else { dir };
+    Command::new("cmd.exe")
+        .arg("/K")
+        .raw_arg(command_line) // passed on as typed, cmd does its own quoting
+        .current_dir(dir)
+        .spawn()?;
+    Ok(())
+}
+
+// human readable size, e.g. 1.5 MB
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = size as f64;
+    let mut unit = 0;
+    while size >= 1024. && unit < UNITS.len() - 1 {
+        size /= 1024.;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} {}", size, UNITS[unit]) } else { format!("{:.1} {}", size, UNITS[unit]) }
+}
+
 pub fn get_icon(path: &str) -> Option<slint::Image> {
     #[repr(C)]
     struct Iconheader {
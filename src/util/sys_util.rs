@@ -9,6 +9,11 @@ use windows::Win32::UI::Shell::ShellExecuteW;
 use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
 use windows::Win32::UI::Input::KeyboardAndMouse::EnableWindow;
 use windows::Win32::Globalization::GetUserDefaultLocaleName;
+use windows::Win32::System::DataExchange::{GetClipboardSequenceNumber, GetClipboardOwner, RegisterClipboardFormatW, IsClipboardFormatAvailable};
+use windows::Win32::System::Threading::{OpenProcess, QueryFullProcessImageNameW, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_NAME_WIN32};
+use windows::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId;
+use windows::Win32::Foundation::CloseHandle;
+use windows::core::{w, PWSTR};
 
 use windows::Win32::{
     Foundation::{HWND, POINT, RECT},
@@ -128,4 +133,38 @@ pub fn get_scale_factor(monitor_id: u32) -> f32 {
             .unwrap_or_else(|e| log_util::log_error(format!("GetDpiForMonitor error: {:?}", e)));
     }
     dpi_x as f32 / 96.0
-}
\ No newline at end of file
+}
+
+// changes whenever the content of the clipboard changes
+pub fn get_clipboard_sequence() -> u32 {
+    unsafe { GetClipboardSequenceNumber() }
+}
+
+// password managers mark what they copy to keep it out of clipboard histories
+pub fn is_clipboard_private() -> bool {
+    unsafe {
+        let format = RegisterClipboardFormatW(w!("ExcludeClipboardContentFromMonitorProcessing"));
+        format != 0 && IsClipboardFormatAvailable(format).is_ok()
+    }
+}
+
+// the executable name of the program that put the content on the clipboard, e.g. notepad.exe
+pub fn get_clipboard_owner_process_name() -> Option<String> {
+    unsafe {
+        let owner = GetClipboardOwner().ok()?;
+        if owner.is_invalid() { return None; }
+        let mut process_id: u32 = 0;
+        GetWindowThreadProcessId(owner, Some(&mut process_id));
+        if process_id == 0 { return None; }
+
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id).ok()?;
+        let mut buffer = [0u16; 1024];
+        let mut size = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut size);
+        let _ = CloseHandle(process);
+        result.ok()?;
+
+        let path = String::from_utf16_lossy(&buffer[..size as usize]);
+        path.rsplit('\\').next().map(str::to_string)
+    }
+}